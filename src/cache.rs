@@ -0,0 +1,160 @@
+use crate::errors::LadonError;
+use crate::ffprobe::ProbeError;
+use crate::ffprobe::probers::Prober;
+use crate::utils::list::{escaped, unescaped};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env::var_os;
+use std::fs::{canonicalize, create_dir_all, metadata, read, remove_file, rename, write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the cache layout changes, older caches are then ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// `$XDG_CACHE_HOME/ladon/probes.json`, or `~/.cache/ladon/probes.json` when XDG_CACHE_HOME is not set.
+pub fn default_cache_location() -> Option<String> {
+    let cache_home = match var_os("XDG_CACHE_HOME") {
+        Some(cache_home) if !cache_home.is_empty() => PathBuf::from(cache_home),
+        _ => PathBuf::from(var_os("HOME")?).join(".cache"),
+    };
+    return Some(cache_home.join("ladon").join("probes.json").to_string_lossy().to_string());
+}
+
+/// A media is probed again as soon as its size or its modification time changes.
+#[derive(PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+fn fingerprint(media_location: &Path) -> Option<Fingerprint> {
+    let media_metadata = metadata(media_location).ok()?;
+    let modified = media_metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    return Some(Fingerprint {
+        size: media_metadata.len(),
+        modified_secs: modified.as_secs(),
+        modified_nanos: modified.subsec_nanos(),
+    });
+}
+
+/// Escaped canonical path, so that medias whose paths are not valid UTF-8 keep distinct entries.
+fn cache_key(media_location: &Path) -> String {
+    return match canonicalize(media_location) {
+        Ok(path) => escaped(&path),
+        Err(_) => escaped(media_location),
+    };
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    output: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: HashMap<String, CacheEntry>,
+}
+
+fn read_entries(cache_location: &str) -> HashMap<String, CacheEntry> {
+    let content = match read(cache_location) {
+        Ok(content) => content,
+        Err(_) => return HashMap::new(),
+    };
+    return match serde_json::from_slice::<CacheFile>(&content) {
+        Ok(cache_file) if cache_file.version == CACHE_VERSION => cache_file.entries,
+        Ok(_) => HashMap::new(),
+        Err(e) => {
+            eprintln!("Ignoring unreadable probe cache {cache_location:?} : {e}");
+            HashMap::new()
+        },
+    };
+}
+
+/// Written to a temporary file renamed over the previous cache, so that a crash or a concurrent search
+/// never leaves a truncated cache behind.
+fn write_cache(cache_location: &Path, cache_file: &CacheFile) -> Result<(), LadonError> {
+    if let Some(cache_directory) = cache_location.parent() {
+        create_dir_all(cache_directory).map_err(|e| LadonError::io("creating probe cache directory", cache_directory, e))?;
+    }
+    let content = serde_json::to_vec(cache_file).map_err(|e| LadonError::io("serializing probe cache", cache_location, e.into()))?;
+    let mut temporary_name = cache_location.as_os_str().to_os_string();
+    temporary_name.push(format!(".{}.tmp", process::id()));
+    let temporary_location = PathBuf::from(temporary_name);
+    write(&temporary_location, content).map_err(|e| LadonError::io("writing probe cache", &temporary_location, e))?;
+    return match rename(&temporary_location, cache_location) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            remove_file(&temporary_location);
+            Err(LadonError::io("replacing probe cache", cache_location, e))
+        },
+    };
+}
+
+/// Prober remembering the ffprobe outputs of unchanged medias between searches.
+/// Failed probes are not cached, those medias are probed again on the next search.
+pub struct CachedProber<'a> {
+    prober: &'a dyn Prober,
+    cache_location: String,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl<'a> CachedProber<'a> {
+
+    /// Loads the cache, or starts an empty one when `rebuild` is set.
+    pub fn new(prober: &'a dyn Prober, cache_location: &str, rebuild: bool) -> CachedProber<'a> {
+        CachedProber {
+            prober,
+            cache_location: cache_location.to_string(),
+            entries: Mutex::new(if rebuild { HashMap::new() } else { read_entries(cache_location) }),
+        }
+    }
+
+    fn cached_output(&self, key: &str, fingerprint: &Fingerprint) -> Option<Value> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        return match entries.get(key) {
+            Some(entry) if entry.fingerprint.eq(fingerprint) => Some(entry.output.clone()),
+            _ => None,
+        };
+    }
+
+    fn cache(&self, key: String, fingerprint: Fingerprint, output: &Value) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key, CacheEntry { fingerprint, output: output.clone() });
+    }
+
+    /// Entries of medias that no longer exist are dropped, a failed save is reported without failing the run.
+    pub fn save(self) {
+        let mut entries = self.entries.into_inner().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|key, _| unescaped(key.as_bytes()).is_some_and(|media_location| media_location.exists()));
+        let cache_file = CacheFile { version: CACHE_VERSION, entries };
+        if let Err(e) = write_cache(Path::new(&self.cache_location), &cache_file) {
+            eprintln!("Error: {e}");
+        }
+    }
+
+}
+
+impl Prober for CachedProber<'_> {
+    fn probe_output(&self, media_location: &Path) -> Result<Value, ProbeError> {
+        let fingerprint = match fingerprint(media_location) {
+            Some(fingerprint) => fingerprint,
+            None => return self.prober.probe_output(media_location),
+        };
+        let key = cache_key(media_location);
+        if let Some(output) = self.cached_output(&key, &fingerprint) {
+            return Ok(output);
+        }
+        let result = self.prober.probe_output(media_location);
+        if let Ok(output) = &result {
+            self.cache(key, fingerprint, output);
+        }
+        return result;
+    }
+}
@@ -0,0 +1,118 @@
+use crate::ffprobe::formats::Format;
+use crate::ffprobe::streams::{Stream, StreamClass};
+use crate::failures::{Failure, Rule, allowed};
+use crate::profile::Profile;
+use crate::utils::media::{extension_of, MEDIA_EXTENSIONS};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// QuickTime major brand, the only way to tell a MOV from an MP4 since ffprobe uses one demuxer for both.
+const QUICKTIME_BRAND: &str = "qt";
+
+const WEBM_VIDEO_CODECS: [&str; 3] = ["vp8", "vp9", "av1"];
+const WEBM_AUDIO_CODECS: [&str; 2] = ["opus", "vorbis"];
+
+/// ffprobe reads WebM with the Matroska demuxer, a Matroska media is WebM when it is named so
+/// or when it only holds the video and audio codecs WebM allows.
+fn is_webm(media_location: &Path, streams: &[Stream]) -> bool {
+    if extension_of(media_location).is_some_and(|extension| extension.eq("webm")) {
+        return true;
+    }
+    let has_codec = |stream: &Stream, codecs: &[&str]| stream.get_name().is_some_and(|name| codecs.contains(&name.to_ascii_lowercase().as_str()));
+    let played_streams: Vec<&Stream> = streams.iter()
+    .filter(|stream| matches!(stream.class(), StreamClass::Video | StreamClass::Audio))
+    .collect();
+    return !played_streams.is_empty() && played_streams.iter().all(|stream| match stream.class() {
+        StreamClass::Video => has_codec(stream, &WEBM_VIDEO_CODECS),
+        _ => has_codec(stream, &WEBM_AUDIO_CODECS),
+    });
+}
+
+/// Container names a profile can use for a format, ffprobe `format_name` being a list of demuxer names :
+/// `mov,mp4,m4a,3gp,3g2,mj2` is named `mp4` (or `mov` for QuickTime files), `matroska,webm` is named `mkv`,
+/// and also `webm` for WebM medias.
+pub fn container_names(media_location: &Path, format: &Format, streams: &[Stream]) -> Vec<String> {
+    let demuxers: Vec<&str> = format.get_format_name().split(',').collect();
+    let mut names: Vec<String> = Vec::new();
+    if demuxers.contains(&"mov") {
+        let brand = format.get_tags().get("major_brand").map_or("", |brand| brand.trim());
+        let aliases: &[&str] = if brand.eq(QUICKTIME_BRAND) { &["mov"] } else { &["mp4", "m4v", "m4a", "3gp", "3g2", "mj2"] };
+        names.extend(aliases.iter().map(|alias| alias.to_string()));
+    } else if demuxers.contains(&"matroska") {
+        names.extend(["mkv", "matroska"].map(str::to_string));
+        if is_webm(media_location, streams) {
+            names.push("webm".to_string());
+        }
+    } else {
+        names.extend(demuxers.iter().map(|demuxer| demuxer.to_string()));
+        let aliases: &[&str] = match demuxers[0] {
+            "mpegts" => &["ts", "m2ts"],
+            "mpeg" | "mpegvideo" => &["mpg", "mpeg", "vob"],
+            "asf" => &["wmv"],
+            _ => &[],
+        };
+        names.extend(aliases.iter().map(|alias| alias.to_string()));
+    }
+    return names;
+}
+
+pub fn is_mp4_family(format: &Format) -> bool {
+    return format.get_format_name().split(',').any(|demuxer| demuxer.eq("mov"));
+}
+
+fn read_atom_header(file: &mut File, offset: u64, file_length: u64) -> Option<(u64, [u8; 4])> {
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut header).ok()?;
+    let kind = [header[4], header[5], header[6], header[7]];
+    let size = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+        0 => file_length - offset,
+        1 => {
+            let mut large_size = [0u8; 8];
+            file.read_exact(&mut large_size).ok()?;
+            u64::from_be_bytes(large_size)
+        },
+        size => size as u64,
+    };
+    return if size < 8 { None } else { Some((size, kind)) };
+}
+
+/// Whether the `moov` atom of an MP4 is written before its `mdat` atom, so that players can start
+/// streaming before the whole file is downloaded. None when the top level atoms cannot be read.
+pub fn is_faststart(media_location: &Path) -> Option<bool> {
+    let mut file = File::open(media_location).ok()?;
+    let file_length = file.metadata().ok()?.len();
+    let mut offset: u64 = 0;
+    while file_length.saturating_sub(offset) >= 8 {
+        let (size, kind) = read_atom_header(&mut file, offset, file_length)?;
+        match &kind {
+            b"moov" => return Some(true),
+            b"mdat" => return Some(false),
+            _ => offset = offset.checked_add(size)?,
+        }
+    }
+    return None;
+}
+
+/// Container of the media, as ffprobe reads it, checked against the profile container rules.
+pub fn container_failures(media_location: &Path, format: &Format, streams: &[Stream], profile: &Profile) -> Vec<Failure> {
+    let container_rules = profile.container();
+    let names = container_names(media_location, format, streams);
+    let container = names.first().map_or(format.get_format_name().as_str(), |name| name.as_str());
+    let mut failures = Vec::new();
+    if names.iter().any(|name| container_rules.denies_format(name)) {
+        failures.push(Failure::of_media(Rule::Container, format!("container {container} denied")));
+    } else if !names.iter().any(|name| container_rules.allows_format(name)) {
+        failures.push(Failure::of_media(Rule::Container, format!("container {container} not in {}", allowed(container_rules.formats()))));
+    }
+    if let Some(extension) = extension_of(media_location).filter(|extension| MEDIA_EXTENSIONS.contains(&extension.as_str())) {
+        if container_rules.requires_matching_extension() && !names.contains(&extension) {
+            failures.push(Failure::of_media(Rule::ExtensionMismatch, format!("extension {extension} but container is {container}")));
+        }
+    }
+    if container_rules.requires_faststart() && is_mp4_family(format) && is_faststart(media_location) == Some(false) {
+        failures.push(Failure::of_media(Rule::Faststart, "needs faststart remux, moov atom after mdat".to_string()));
+    }
+    return failures;
+}
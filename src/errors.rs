@@ -0,0 +1,119 @@
+use crate::ffmpeg::DecodeError;
+use crate::ffprobe::ProbeError;
+use std::fmt::{self, Debug, Display};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Everything that can stop a command, each error telling the process exit code it leads to.
+pub enum LadonError {
+    /// A path given to the command does not lead to what the command reads
+    NoInput { path: PathBuf, reason: &'static str },
+    /// A path given to the command cannot be written
+    CannotCreate { path: PathBuf, reason: &'static str },
+    /// A file system operation failed on a path
+    Io { action: &'static str, path: PathBuf, source: io::Error },
+    Probe { path: PathBuf, source: ProbeError },
+    Decode { path: PathBuf, source: DecodeError },
+    NotMedia(PathBuf),
+    InvalidList { path: PathBuf, line: usize },
+    /// A transcoded media has no media of the same name in the library
+    NotImported(PathBuf),
+    Profile { name: String, reason: String },
+    /// Files that could not be processed while the run kept going, with the exit code of the first one
+    KeptGoing { count: usize, exit_code: exitcode::ExitCode },
+}
+
+impl LadonError {
+
+    pub fn io(action: &'static str, path: impl Into<PathBuf>, source: io::Error) -> LadonError {
+        LadonError::Io { action, path: path.into(), source }
+    }
+
+    pub fn exit_code(&self) -> exitcode::ExitCode {
+        return match self {
+            LadonError::NoInput { .. } => exitcode::NOINPUT,
+            LadonError::CannotCreate { .. } => exitcode::CANTCREAT,
+            LadonError::Io { .. } => exitcode::IOERR,
+            LadonError::Probe { source: ProbeError::Spawn(_), .. } => exitcode::UNAVAILABLE,
+            LadonError::Probe { source: ProbeError::Timeout(_), .. } => exitcode::TEMPFAIL,
+            LadonError::Probe { .. } => exitcode::DATAERR,
+            LadonError::Decode { source: DecodeError::Spawn(_), .. } => exitcode::UNAVAILABLE,
+            LadonError::Decode { source: DecodeError::Timeout(_), .. } => exitcode::TEMPFAIL,
+            LadonError::Decode { .. } => exitcode::IOERR,
+            LadonError::NotMedia(_) | LadonError::InvalidList { .. } | LadonError::NotImported(_) => exitcode::DATAERR,
+            LadonError::Profile { .. } => exitcode::CONFIG,
+            LadonError::KeptGoing { exit_code, .. } => *exit_code,
+        };
+    }
+
+}
+
+impl Display for LadonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            LadonError::NoInput { path, reason } | LadonError::CannotCreate { path, reason } => write!(f, "{} {reason}", path.display()),
+            LadonError::Io { action, path, source } => write!(f, "error {action} {} : {source}", path.display()),
+            LadonError::Probe { path, source } => write!(f, "cannot probe {} : {source}", path.display()),
+            LadonError::Decode { path, source } => write!(f, "cannot decode {} : {source}", path.display()),
+            LadonError::NotMedia(path) => write!(f, "{} is not a media file", path.display()),
+            LadonError::InvalidList { path, line } => write!(f, "invalid escape sequence line {line} of {}", path.display()),
+            LadonError::NotImported(path) => write!(f, "{} has no media to replace in the target directory", path.display()),
+            LadonError::Profile { name, reason } => write!(f, "invalid profile {name:?} : {reason}"),
+            LadonError::KeptGoing { count, .. } => write!(f, "{count} files could not be processed"),
+        };
+    }
+}
+
+impl Debug for LadonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return Display::fmt(self, f);
+    }
+}
+
+/// Errors met on single files. With `--keep-going` they are logged and the run goes on,
+/// otherwise the first one stops the run.
+pub struct ErrorLog {
+    keep_going: bool,
+    errors: Mutex<Vec<LadonError>>,
+}
+
+impl ErrorLog {
+
+    pub fn new(keep_going: bool) -> ErrorLog {
+        ErrorLog { keep_going, errors: Mutex::new(Vec::new()) }
+    }
+
+    /// Hands the error back when the run has to stop.
+    pub fn keep(&self, error: LadonError) -> Result<(), LadonError> {
+        if !self.keep_going {
+            return Err(error);
+        }
+        eprintln!("Error: {error}");
+        self.errors.lock().unwrap_or_else(|e| e.into_inner()).push(error);
+        return Ok(());
+    }
+
+    /// The value of a result, or None when its error is kept.
+    pub fn skip<T>(&self, result: Result<T, LadonError>) -> Result<Option<T>, LadonError> {
+        return match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => self.keep(error).map(|_| None),
+        };
+    }
+
+    /// Prints every kept error, the run fails when there is any.
+    pub fn summary(self) -> Result<(), LadonError> {
+        let errors = self.errors.into_inner().unwrap_or_else(|e| e.into_inner());
+        let exit_code = match errors.first() {
+            Some(first_error) => first_error.exit_code(),
+            None => return Ok(()),
+        };
+        eprintln!("\n{} files could not be processed :", errors.len());
+        for error in &errors {
+            eprintln!("  - {error}");
+        }
+        return Err(LadonError::KeptGoing { count: errors.len(), exit_code });
+    }
+
+}
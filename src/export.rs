@@ -1,40 +1,51 @@
+use crate::errors::{ErrorLog, LadonError};
 use crate::utils::validation::{validate_directory, validate_input_file};
-use crate::utils::file::{file_name, read_lines};
-use crate::utils::media::is_media;
+use crate::utils::file::file_name;
+use crate::utils::list::{ListFormat, read_list};
+use crate::utils::media::MediaDetector;
 use crate::terminal::{Terminal, ExportTerm};
 use std::fs::copy;
+use std::path::{Path, PathBuf};
 
-fn is_valid(media_location: &String) -> bool {
-    validate_input_file(media_location);
-    return if is_media(media_location) { true } else { panic!("{media_location} is not a media file.") }
+fn checked_media(media_location: PathBuf, detector: &MediaDetector) -> Result<PathBuf, LadonError> {
+    validate_input_file(&media_location)?;
+    return if detector.is_media(&media_location) { Ok(media_location) } else { Err(LadonError::NotMedia(media_location)) }
 }
 
-fn get_medias(medias_list_location: &str) -> Vec<String> {
-    return read_lines(medias_list_location)
-    .filter(is_valid)
-    .collect(); 
+/// Every listed media is checked before the first copy, with the same detection as the search that wrote the list.
+fn get_medias(medias_list_location: &Path, list_format: ListFormat, detector: &MediaDetector, errors: &ErrorLog) -> Result<Vec<PathBuf>, LadonError> {
+    let mut medias = Vec::new();
+    for listed_media in read_list(medias_list_location, list_format)? {
+        if let Some(media_location) = errors.skip(listed_media.and_then(|media_location| checked_media(media_location, detector)))? {
+            medias.push(media_location);
+        }
+    }
+    return Ok(medias);
 }
 
-fn export(media_location: &str, export_directory_location: &str) {
-    let media_name = file_name(media_location);
-    let destination = format!("{export_directory_location}/{media_name}");
-    copy(media_location, destination);
+fn export(media_location: &Path, export_directory_location: &Path) -> Result<(), LadonError> {
+    let destination = export_directory_location.join(file_name(media_location));
+    return match copy(media_location, &destination) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(LadonError::io("copying", media_location, e)),
+    };
 }
 
-fn process_medias_export(medias_list_location: &str, export_directory_location: &str) {
-    let lines = get_medias(medias_list_location);
-    let mut exporter_terminal: Terminal = ExportTerm::new(lines.len(), export_directory_location);
+fn process_medias_export(medias_list_location: &Path, list_format: ListFormat, export_directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog) -> Result<(), LadonError> {
+    let medias = get_medias(medias_list_location, list_format, detector, errors)?;
+    let export_directory_display = export_directory_location.display().to_string();
+    let mut exporter_terminal: Terminal = ExportTerm::new(medias.len(), &export_directory_display);
     exporter_terminal.export_start();
-    for line in lines {
-        let media_location = line.as_str();
-        exporter_terminal.update_export(media_location);
-        export(media_location, export_directory_location);
+    for media_location in medias {
+        exporter_terminal.update_export(&media_location.display().to_string());
+        errors.skip(export(&media_location, export_directory_location))?;
     }
     exporter_terminal.export_done();
+    return Ok(());
 }
 
-pub fn export_medias(medias_list_location: &str, export_directory_location: &str) {
-    validate_input_file(medias_list_location);
-    validate_directory(export_directory_location);
-    process_medias_export(medias_list_location, export_directory_location);
-}
\ No newline at end of file
+pub fn export_medias(medias_list_location: &Path, list_format: ListFormat, export_directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog) -> Result<(), LadonError> {
+    validate_input_file(medias_list_location)?;
+    validate_directory(export_directory_location)?;
+    return process_medias_export(medias_list_location, list_format, export_directory_location, detector, errors);
+}
@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+/// Compatibility rule a media failed, written as a snake case reason code in reports.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    Container,
+    Faststart,
+    ExtensionMismatch,
+    VideoCodec,
+    VideoProfile,
+    VideoLevel,
+    VideoBitDepth,
+    VideoPixelFormat,
+    VideoHdr10,
+    VideoHlg,
+    VideoDolbyVision,
+    VideoInterlaced,
+    VideoVariableFrameRate,
+    VideoDownscale,
+    AudioCodec,
+    AudioProfile,
+    AudioChannels,
+    AudioChannelLayout,
+    AudioSampleRate,
+    AudioBitRate,
+    AudioLanguage,
+    DefaultAudioLanguage,
+    SubtitleLanguage,
+    SubtitleKind,
+    CoverArt,
+    TimedThumbnails,
+    Attachment,
+    Data,
+}
+
+impl Rule {
+
+    pub fn code(&self) -> &'static str {
+        return match self {
+            Self::Container => "container",
+            Self::Faststart => "faststart",
+            Self::ExtensionMismatch => "extension_mismatch",
+            Self::VideoCodec => "video_codec",
+            Self::VideoProfile => "video_profile",
+            Self::VideoLevel => "video_level",
+            Self::VideoBitDepth => "video_bit_depth",
+            Self::VideoPixelFormat => "video_pixel_format",
+            Self::VideoHdr10 => "video_hdr10",
+            Self::VideoHlg => "video_hlg",
+            Self::VideoDolbyVision => "video_dolby_vision",
+            Self::VideoInterlaced => "video_interlaced",
+            Self::VideoVariableFrameRate => "video_variable_frame_rate",
+            Self::VideoDownscale => "video_downscale",
+            Self::AudioCodec => "audio_codec",
+            Self::AudioProfile => "audio_profile",
+            Self::AudioChannels => "audio_channels",
+            Self::AudioChannelLayout => "audio_channel_layout",
+            Self::AudioSampleRate => "audio_sample_rate",
+            Self::AudioBitRate => "audio_bit_rate",
+            Self::AudioLanguage => "audio_language",
+            Self::DefaultAudioLanguage => "default_audio_language",
+            Self::SubtitleLanguage => "subtitle_language",
+            Self::SubtitleKind => "subtitle_kind",
+            Self::CoverArt => "cover_art",
+            Self::TimedThumbnails => "timed_thumbnails",
+            Self::Attachment => "attachment",
+            Self::Data => "data",
+        };
+    }
+
+}
+
+/// Why a media needs transcoding, for example `audio #1 dts not in [aac]`.
+#[derive(Serialize)]
+pub struct Failure {
+    rule: Rule,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_type: Option<String>,
+    message: String,
+}
+
+impl Failure {
+
+    /// Failure of the whole media : its container, or streams it lacks
+    pub fn of_media(rule: Rule, message: String) -> Failure {
+        Failure {
+            rule,
+            stream_index: None,
+            stream_type: None,
+            message,
+        }
+    }
+
+    pub fn of_stream(rule: Rule, stream_index: u32, stream_type: &str, message: String) -> Failure {
+        Failure {
+            rule,
+            stream_index: Some(stream_index),
+            stream_type: Some(stream_type.to_string()),
+            message,
+        }
+    }
+
+    pub fn get_rule(&self) -> Rule {
+        return self.rule;
+    }
+
+    pub fn get_stream_index(&self) -> Option<u32> {
+        return self.stream_index;
+    }
+
+    pub fn get_stream_type(&self) -> Option<&String> {
+        return self.stream_type.as_ref();
+    }
+
+    pub fn get_message(&self) -> &String {
+        return &self.message;
+    }
+
+}
+
+/// Allowed values as written in messages : `[h264, hevc]`.
+pub fn allowed(values: &[String]) -> String {
+    return format!("[{}]", values.join(", "));
+}
@@ -0,0 +1,140 @@
+use crate::ffprobe::{drain, drained, kill};
+use std::fmt::{self, Debug, Display};
+use std::io::Error;
+use std::path::Path;
+use std::process::{Command, Stdio, Child};
+use std::time::Duration;
+use wait_timeout::ChildExt;
+
+/// Reasons why ffmpeg could not tell whether a media decodes.
+pub enum DecodeError {
+    /// ffmpeg could not be started, it is probably not installed
+    Spawn(Error),
+    /// ffmpeg process could not be awaited
+    Wait(Error),
+    /// ffmpeg did not decode the media in time and was killed
+    Timeout(Duration),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawn(e) => write!(f, "cannot start ffmpeg : {e}"),
+            Self::Wait(e) => write!(f, "cannot wait for ffmpeg : {e}"),
+            Self::Timeout(timeout) => write!(f, "ffmpeg did not decode within {}s", timeout.as_secs()),
+        }
+    }
+}
+
+impl Debug for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return Display::fmt(self, f);
+    }
+}
+
+/// Lines written by ffmpeg on stderr, an exit error without message gets its own line.
+fn decoder_errors(stderr: &[u8], code: Option<i32>, success: bool) -> Vec<String> {
+    let mut errors: Vec<String> = String::from_utf8_lossy(stderr).lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .map(str::to_string)
+    .collect();
+    if !success && errors.is_empty() {
+        errors.push(match code {
+            Some(code) => format!("ffmpeg exited with {code}"),
+            None => "ffmpeg was interrupted".to_string(),
+        });
+    }
+    return errors;
+}
+
+fn ffmpeg_errors(mut ffmpeg_process: Child, timeout: Duration) -> Result<Vec<String>, DecodeError> {
+    let stderr_reader = drain(ffmpeg_process.stderr.take());
+    return match ffmpeg_process.wait_timeout(timeout) {
+        Ok(Some(status)) => Ok(decoder_errors(&drained(stderr_reader), status.code(), status.success())),
+        Ok(None) => {
+            kill(&mut ffmpeg_process);
+            Err(DecodeError::Timeout(timeout))
+        },
+        Err(e) => {
+            kill(&mut ffmpeg_process);
+            Err(DecodeError::Wait(e))
+        },
+    };
+}
+
+/// Decodes every stream of the media without writing anything, only errors are logged.
+fn ffmpeg(media_location: &Path, timeout: Duration) -> Result<Vec<String>, DecodeError> {
+    let ffmpeg_result = Command::new("ffmpeg")
+    .args(["-nostdin", "-v", "error", "-i"])
+    .arg(media_location)
+    .args(["-f", "null", "-"])
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn();
+    return match ffmpeg_result {
+        Ok(ffmpeg_process) => ffmpeg_errors(ffmpeg_process, timeout),
+        Err(e) => Err(DecodeError::Spawn(e)),
+    };
+}
+
+pub mod decoders {
+    use crate::ffmpeg::{ffmpeg, decoder_errors, DecodeError};
+    use crate::utils::file::file_name;
+    use std::fs::read;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Decodes a media to find broken frames and truncated streams.
+    /// Decoders are shared by the verify workers.
+    pub trait Decoder: Sync {
+        /// Decoder errors, none when the media decodes cleanly
+        fn decode_errors(&self, media_location: &Path) -> Result<Vec<String>, DecodeError>;
+    }
+
+    /// Runs the ffmpeg binary found in the PATH.
+    pub struct FfmpegCli {
+        timeout: Duration,
+    }
+
+    impl FfmpegCli {
+
+        pub fn new(timeout: Duration) -> FfmpegCli {
+            FfmpegCli { timeout }
+        }
+
+    }
+
+    impl Decoder for FfmpegCli {
+        fn decode_errors(&self, media_location: &Path) -> Result<Vec<String>, DecodeError> {
+            return ffmpeg(media_location, self.timeout);
+        }
+    }
+
+    /// Replays ffmpeg errors recorded in a fixtures directory : `movie.mkv` fails to decode with the
+    /// errors from `movie.mkv.ffmpeg.stderr`, and decodes cleanly without it.
+    pub struct FixtureDecoder {
+        fixtures_directory: String,
+    }
+
+    impl FixtureDecoder {
+
+        pub fn new(fixtures_directory: &str) -> FixtureDecoder {
+            FixtureDecoder { fixtures_directory: fixtures_directory.to_string() }
+        }
+
+    }
+
+    impl Decoder for FixtureDecoder {
+        fn decode_errors(&self, media_location: &Path) -> Result<Vec<String>, DecodeError> {
+            let mut fixture_name = file_name(media_location).to_os_string();
+            fixture_name.push(".ffmpeg.stderr");
+            return match read(Path::new(&self.fixtures_directory).join(fixture_name)) {
+                Ok(stderr) => Ok(decoder_errors(&stderr, Some(1), false)),
+                Err(_) => Ok(Vec::new()),
+            };
+        }
+    }
+
+}
@@ -1,125 +1,361 @@
-use crate::ffprobe::streams::Stream;
-use crate::ffprobe::parsers::to_stream;
-use std::process::{Command, Stdio, Child, ChildStdout};
-use std::io::{Result, BufReader, BufRead, Lines, Error, ErrorKind};
+use crate::ffprobe::streams::{Stream, audio_language_failures};
+use crate::ffprobe::formats::Format;
+use crate::failures::Failure;
+use crate::profile::Profile;
+use crate::container::container_failures;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt::{self, Debug, Display};
+use std::process::{Command, Stdio, Child};
+use std::io::{Read, Error};
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use wait_timeout::ChildExt;
 
-fn get_stream(option: Option<Stream>) -> Stream {
-    return match option {
-        Some(stream) => stream,
-        None => panic!("Found empty stream after filtering!")
+/// Everything ffprobe reports about a media : its container and its streams.
+#[derive(Deserialize)]
+pub struct Probe {
+    #[serde(default)]
+    streams: Vec<Stream>,
+    format: Option<Format>,
+}
+
+impl Probe {
+
+    pub fn streams(&self) -> &Vec<Stream> {
+        return &self.streams;
+    }
+
+    pub fn format(&self) -> Option<&Format> {
+        return self.format.as_ref();
+    }
+
+    /// Every rule of the profile the media does not follow, container rules first.
+    pub fn failures(&self, media_location: &Path, profile: &Profile) -> Vec<Failure> {
+        let mut failures = match self.format() {
+            Some(format) => container_failures(media_location, format, &self.streams, profile),
+            None => Vec::new(),
+        };
+        failures.extend(self.streams.iter().flat_map(|stream| stream.failures(profile)));
+        failures.extend(audio_language_failures(&self.streams, profile));
+        return failures;
+    }
+
+}
+
+/// Reasons why ffprobe could not describe a media.
+pub enum ProbeError {
+    /// ffprobe could not be started, it is probably not installed
+    Spawn(Error),
+    /// ffprobe process could not be awaited
+    Wait(Error),
+    /// ffprobe exited with an error, the media cannot be read
+    Unreadable { code: Option<i32>, stderr: String },
+    /// ffprobe did not answer in time and was killed
+    Timeout(Duration),
+    /// ffprobe answered something that is not a valid JSON description
+    InvalidOutput(serde_json::Error),
+}
+
+impl Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spawn(e) => write!(f, "cannot start ffprobe : {e}"),
+            Self::Wait(e) => write!(f, "cannot wait for ffprobe : {e}"),
+            Self::Unreadable { code: Some(code), stderr } => write!(f, "unreadable media, ffprobe exited with {code} : {}", stderr.trim()),
+            Self::Unreadable { code: None, stderr } => write!(f, "unreadable media, ffprobe was interrupted : {}", stderr.trim()),
+            Self::Timeout(timeout) => write!(f, "ffprobe did not answer within {}s", timeout.as_secs()),
+            Self::InvalidOutput(e) => write!(f, "invalid ffprobe output : {e}"),
+        }
     }
 }
 
-fn to_line(result: Result<String>) -> String {
-    return match result {
-        Ok(line) => line,
-        Err(e) => panic!("Error reading line : {e:?}")
+impl Debug for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return Display::fmt(self, f);
     }
 }
 
-fn stdout_reader(mut ffprobe_process: Child) -> Result<BufReader<ChildStdout>> {
-    let opt = ffprobe_process.stdout.take();
-    return match opt {
-        Some(stdout) => Ok(BufReader::new(stdout)),
-        None => Err(Error::new(ErrorKind::Other, "Cannot get stdout from process")),
-    };
+/// Time left to a killed ffprobe or ffmpeg to exit before it is abandoned, a process stuck on a hung mount may never die.
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// Pipes are drained on their own threads so that a verbose process never blocks on a full pipe.
+pub fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    return thread::spawn(move || {
+        let mut content = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut content);
+        }
+        return content;
+    });
 }
 
-fn stdout_streams(mut ffprobe_process: Child) -> Result<impl Iterator<Item = Stream>> {
-    return match stdout_reader(ffprobe_process) {
-        Ok(stdout_reader) => Ok(
-                stdout_reader.lines()
-                .map(to_line)
-                .map(to_stream)
-                .filter(Option::is_some)
-                .map(get_stream)
-            ),
-        Err(e) => Err(e),
-    };
+pub fn drained(pipe_reader: JoinHandle<Vec<u8>>) -> Vec<u8> {
+    return pipe_reader.join().unwrap_or_default();
 }
 
-fn ffprobe_output(result: Result<Child>) -> Result<impl Iterator<Item = Stream>> {
-    return match result {
-        Ok(ffprobe_process) => stdout_streams(ffprobe_process),
-        Err(e) => Err(e),
+pub fn kill(process: &mut Child) {
+    process.kill();
+    process.wait_timeout(KILL_GRACE);
+}
+
+/// ffprobe copies file names and tag values as raw bytes, those that are not valid UTF-8 are replaced
+/// so that a single Latin-1 name does not make the whole output unreadable.
+fn parse_output(output: &[u8]) -> Result<Value, ProbeError> {
+    return serde_json::from_str(&String::from_utf8_lossy(output)).map_err(ProbeError::InvalidOutput);
+}
+
+fn ffprobe_output(mut ffprobe_process: Child, timeout: Duration) -> Result<Value, ProbeError> {
+    let stdout_reader = drain(ffprobe_process.stdout.take());
+    let stderr_reader = drain(ffprobe_process.stderr.take());
+    return match ffprobe_process.wait_timeout(timeout) {
+        Ok(Some(status)) if status.success() => parse_output(&drained(stdout_reader)),
+        Ok(Some(status)) => Err(ProbeError::Unreadable {
+            code: status.code(),
+            stderr: String::from_utf8_lossy(&drained(stderr_reader)).to_string(),
+        }),
+        Ok(None) => {
+            kill(&mut ffprobe_process);
+            Err(ProbeError::Timeout(timeout))
+        },
+        Err(e) => {
+            kill(&mut ffprobe_process);
+            Err(ProbeError::Wait(e))
+        },
     };
 }
 
-pub fn ffprobe<'a>(media_location: &str) -> Result<impl Iterator<Item = Stream>> {
-    let mut ffprobe_result = Command::new("ffprobe")
-    .args(["-show_streams","-loglevel","quiet","-print_format","compact",media_location])
+fn ffprobe(media_location: &Path, timeout: Duration) -> Result<Value, ProbeError> {
+    let ffprobe_result = Command::new("ffprobe")
+    .args(["-loglevel","error","-print_format","json","-show_format","-show_streams"])
+    .arg(media_location)
+    .stdin(Stdio::null())
     .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
     .spawn();
-    return ffprobe_output(ffprobe_result);
+    return match ffprobe_result {
+        Ok(ffprobe_process) => ffprobe_output(ffprobe_process, timeout),
+        Err(e) => Err(ProbeError::Spawn(e)),
+    };
 }
-  
-pub mod parsers {
-    use crate::ffprobe::streams::{Stream, from};
-    use core::str::Split;
-    
-    fn next_or_fail<'a>(pair: &'a mut Split<char>, chunck: &str) -> &'a str {
-        return match pair.next() {
-            Some(value) => value,
-            None => panic!("Invalid stream chunck: {chunck}"),
+
+pub mod probers {
+    use crate::ffprobe::{ffprobe, parse_output, Probe, ProbeError};
+    use crate::utils::file::file_name;
+    use serde_json::Value;
+    use std::fs::read;
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Describes a media, its container and its streams.
+    /// Probers are shared by the search workers.
+    pub trait Prober: Sync {
+        /// JSON document written by ffprobe, kept raw so that it can be cached
+        fn probe_output(&self, media_location: &Path) -> Result<Value, ProbeError>;
+
+        fn probe(&self, media_location: &Path) -> Result<Probe, ProbeError> {
+            return match self.probe_output(media_location) {
+                Ok(output) => serde_json::from_value(output).map_err(ProbeError::InvalidOutput),
+                Err(e) => Err(e),
+            };
         }
     }
-    
-    fn is_valid(codec: &str) -> bool {
-        return match codec {
-            "video" | "audio" | "subtitle" => true,
-            _ => false,
-        };
+
+    /// Runs the ffprobe binary found in the PATH.
+    pub struct FfprobeCli {
+        timeout: Duration,
     }
-    
-    fn build_stream_from(mut chuncks: Split<&str>) -> Option<Stream> {
-        let mut stream = Stream::new();
-        while let Some(chunck) = chuncks.next() {
-            let mut pair = chunck.split('=');
-            let key = next_or_fail(&mut pair, chunck);
-            match key {
-                "codec_type" => {
-                    let codec = next_or_fail(&mut pair, chunck);
-                    if(is_valid(codec)) {
-                        stream.set_codec(from(codec));
-                    } else {
-                        break;
-                    }
-                },
-                "codec_name" => {
-                    let name = next_or_fail(&mut pair, chunck);
-                    stream.set_name(name);
-                },
-                "tag:language" | "TAG:language" | "tag:LANGUAGE" | "TAG:LANGUAGE" => {
-                    let language = next_or_fail(&mut pair, chunck);
-                    stream.set_language(language);
-                },
-                _ => (),
+
+    impl FfprobeCli {
+
+        pub fn new(timeout: Duration) -> FfprobeCli {
+            FfprobeCli { timeout }
+        }
+
+    }
+
+    impl Prober for FfprobeCli {
+        fn probe_output(&self, media_location: &Path) -> Result<Value, ProbeError> {
+            return ffprobe(media_location, self.timeout);
+        }
+    }
+
+    /// Replays ffprobe outputs recorded in a fixtures directory, looked up by media file name :
+    /// `movie.mkv` is described by `movie.mkv.json`, or is unreadable with the error from `movie.mkv.stderr`.
+    pub struct FixtureProber {
+        fixtures_directory: String,
+    }
+
+    impl FixtureProber {
+
+        pub fn new(fixtures_directory: &str) -> FixtureProber {
+            FixtureProber { fixtures_directory: fixtures_directory.to_string() }
+        }
+
+        fn fixture(&self, media_location: &Path, extension: &str) -> Option<Vec<u8>> {
+            let mut fixture_name = file_name(media_location).to_os_string();
+            fixture_name.push(format!(".{extension}"));
+            let fixture_path = Path::new(&self.fixtures_directory).join(fixture_name);
+            return read(fixture_path).ok();
+        }
+
+    }
+
+    impl Prober for FixtureProber {
+        fn probe_output(&self, media_location: &Path) -> Result<Value, ProbeError> {
+            if let Some(output) = self.fixture(media_location, "json") {
+                return parse_output(&output);
+            }
+            return match self.fixture(media_location, "stderr") {
+                Some(stderr) => Err(ProbeError::Unreadable { code: Some(1), stderr: String::from_utf8_lossy(&stderr).to_string() }),
+                None => Err(ProbeError::Spawn(Error::new(ErrorKind::NotFound, format!("no fixture for {}", media_location.display())))),
             };
-            if stream.is_filled() { return Some(stream); }
-        };
-        return None;
+        }
     }
-    
-    pub fn to_stream(line: String) -> Option<Stream> {
-        let mut chuncks = line.split("|");
-        return match chuncks.next() {
-            Some(first_chunck) => if("stream".eq(first_chunck)) { build_stream_from(chuncks) } else { None },
-            None => None,
+
+}
+
+pub mod parsers {
+    use serde::{Deserialize, Deserializer};
+    use serde::de::Error;
+    use std::str::FromStr;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Number(serde_json::Number),
+        Text(String),
+    }
+
+    /// ffprobe writes most numeric values (bit rates, durations, sample rates...) as JSON strings.
+    pub fn number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where D: Deserializer<'de>, T: FromStr {
+        return match Option::<Raw>::deserialize(deserializer)? {
+            Some(Raw::Number(number)) => number.to_string().parse().map(Some).map_err(|_| D::Error::custom(format!("invalid number {number}"))),
+            Some(Raw::Text(text)) if text == "N/A" => Ok(None),
+            Some(Raw::Text(text)) => text.parse().map(Some).map_err(|_| D::Error::custom(format!("invalid number {text:?}"))),
+            None => Ok(None),
         };
     }
 
+    /// ffprobe writes disposition flags as 0 or 1.
+    pub fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+    where D: Deserializer<'de> {
+        return Ok(u8::deserialize(deserializer)? != 0);
+    }
+
+}
+
+pub mod formats {
+    use crate::ffprobe::parsers::number;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    /// Container level information, from the `format` section of ffprobe.
+    #[derive(Deserialize)]
+    pub struct Format {
+        filename: String,
+        #[serde(default)]
+        nb_streams: u32,
+        format_name: String,
+        format_long_name: Option<String>,
+        #[serde(default, deserialize_with = "number")]
+        start_time: Option<f64>,
+        #[serde(default, deserialize_with = "number")]
+        duration: Option<f64>,
+        #[serde(default, deserialize_with = "number")]
+        size: Option<u64>,
+        #[serde(default, deserialize_with = "number")]
+        bit_rate: Option<u64>,
+        probe_score: Option<u32>,
+        #[serde(default)]
+        tags: HashMap<String, String>,
+    }
+
+    impl Format {
+
+        pub fn get_filename(&self) -> &String {
+            return &self.filename;
+        }
+
+        pub fn get_nb_streams(&self) -> u32 {
+            return self.nb_streams;
+        }
+
+        /// Comma separated names of the demuxer, for example `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`
+        pub fn get_format_name(&self) -> &String {
+            return &self.format_name;
+        }
+
+        pub fn get_format_long_name(&self) -> Option<&String> {
+            return self.format_long_name.as_ref();
+        }
+
+        pub fn get_start_time(&self) -> Option<f64> {
+            return self.start_time;
+        }
+
+        pub fn get_duration(&self) -> Option<f64> {
+            return self.duration;
+        }
+
+        pub fn get_size(&self) -> Option<u64> {
+            return self.size;
+        }
+
+        pub fn get_bit_rate(&self) -> Option<u64> {
+            return self.bit_rate;
+        }
+
+        pub fn get_probe_score(&self) -> Option<u32> {
+            return self.probe_score;
+        }
+
+        pub fn get_tags(&self) -> &HashMap<String, String> {
+            return &self.tags;
+        }
+
+    }
+
 }
 
 pub mod streams {
-    use std::borrow::Borrow;
-    use std::cmp::Eq;
+    use crate::ffprobe::parsers::{number, flag};
+    use crate::profile::{Profile, VideoRules, CodecConstraints, AudioConstraints, ExtraStreamRules, levels};
+    use crate::failures::{Failure, Rule, allowed};
+    use serde::Deserialize;
+    use std::collections::HashMap;
     use std::fmt::{self, Debug};
 
-    #[derive(PartialEq, Eq)]
+    pub const UNDETERMINED_LANGUAGE: &str = "und";
+
+    #[derive(PartialEq, Eq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
     pub enum Codec {
         Video,
         Audio,
         Subtitle,
+        Data,
+        Attachment,
+        #[serde(other)]
+        Unknown,
+    }
+
+    impl Codec {
+
+        pub fn name(&self) -> &'static str {
+            return match self {
+                Self::Video => "video",
+                Self::Audio => "audio",
+                Self::Subtitle => "subtitle",
+                Self::Data => "data",
+                Self::Attachment => "attachment",
+                Self::Unknown => "unknown",
+            };
+        }
+
     }
 
     impl Debug for Codec {
@@ -128,59 +364,418 @@ pub mod streams {
                 Self::Video => write!(f, "Video"),
                 Self::Audio => write!(f, "Audio"),
                 Self::Subtitle => write!(f, "Subtitle"),
+                Self::Data => write!(f, "Data"),
+                Self::Attachment => write!(f, "Attachment"),
+                Self::Unknown => write!(f, "Unknown"),
             }
         }
     }
 
-    pub fn from(codec_type: &str) -> Codec {
-        return match codec_type {
-            "video" => Codec::Video,
-            "audio" => Codec::Audio,
-            "subtitle" => Codec::Subtitle,
-            _ => panic!("Unrecognize codec type : '{codec_type:?}'"),
+    /// What a stream is used for, streams of the same codec type are not checked the same way :
+    /// an embedded poster is a video stream but it is never played as a video.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum StreamClass {
+        Video,
+        Audio,
+        Subtitle,
+        /// Poster or cover, as a video stream with the `attached_pic` disposition or an image attachment
+        CoverArt,
+        /// Video stream of thumbnails used for seeking previews
+        TimedThumbnails,
+        /// Non image attachment, fonts for example
+        Attachment,
+        /// Data stream, timecodes or chapters for example
+        Data,
+        Unknown,
+    }
+
+    /// How a subtitle codec is drawn, named after the ffprobe codec name.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum SubtitleKind {
+        /// Text with styles, rendered by the player : subrip, ass, mov_text, webvtt
+        Text,
+        /// Pictures of the subtitles : hdmv_pgs_subtitle, dvd_subtitle, dvb_subtitle
+        Bitmap,
+        Unknown,
+    }
+
+    impl SubtitleKind {
+
+        pub fn of(codec_name: &str) -> SubtitleKind {
+            return match codec_name.to_ascii_lowercase().as_str() {
+                "subrip" | "srt" | "ass" | "ssa" | "mov_text" | "webvtt" | "text" => Self::Text,
+                "hdmv_pgs_subtitle" | "dvd_subtitle" | "dvb_subtitle" | "xsub" => Self::Bitmap,
+                _ => Self::Unknown,
+            };
         }
+
+        pub fn name(&self) -> &'static str {
+            return match self {
+                Self::Text => "text",
+                Self::Bitmap => "bitmap",
+                Self::Unknown => "unknown",
+            };
+        }
+
     }
 
+    impl StreamClass {
+
+        pub fn name(&self) -> &'static str {
+            return match self {
+                Self::Video => "video",
+                Self::Audio => "audio",
+                Self::Subtitle => "subtitle",
+                Self::CoverArt => "cover art",
+                Self::TimedThumbnails => "timed thumbnails",
+                Self::Attachment => "attachment",
+                Self::Data => "data",
+                Self::Unknown => "unknown",
+            };
+        }
+
+    }
+
+    #[derive(Default, Deserialize)]
+    #[serde(default)]
+    pub struct Disposition {
+        #[serde(deserialize_with = "flag")]
+        default: bool,
+        #[serde(deserialize_with = "flag")]
+        dub: bool,
+        #[serde(deserialize_with = "flag")]
+        original: bool,
+        #[serde(deserialize_with = "flag")]
+        comment: bool,
+        #[serde(deserialize_with = "flag")]
+        forced: bool,
+        #[serde(deserialize_with = "flag")]
+        hearing_impaired: bool,
+        #[serde(deserialize_with = "flag")]
+        visual_impaired: bool,
+        #[serde(deserialize_with = "flag")]
+        attached_pic: bool,
+        #[serde(deserialize_with = "flag")]
+        timed_thumbnails: bool,
+        #[serde(deserialize_with = "flag")]
+        captions: bool,
+        #[serde(deserialize_with = "flag")]
+        descriptions: bool,
+    }
+
+    impl Disposition {
+
+        pub fn is_default(&self) -> bool {
+            return self.default;
+        }
+
+        pub fn is_dub(&self) -> bool {
+            return self.dub;
+        }
+
+        pub fn is_original(&self) -> bool {
+            return self.original;
+        }
+
+        pub fn is_comment(&self) -> bool {
+            return self.comment;
+        }
+
+        pub fn is_forced(&self) -> bool {
+            return self.forced;
+        }
+
+        pub fn is_hearing_impaired(&self) -> bool {
+            return self.hearing_impaired;
+        }
+
+        pub fn is_visual_impaired(&self) -> bool {
+            return self.visual_impaired;
+        }
+
+        pub fn is_attached_pic(&self) -> bool {
+            return self.attached_pic;
+        }
+
+        pub fn is_timed_thumbnails(&self) -> bool {
+            return self.timed_thumbnails;
+        }
+
+        pub fn is_captions(&self) -> bool {
+            return self.captions;
+        }
+
+        pub fn is_descriptions(&self) -> bool {
+            return self.descriptions;
+        }
+
+        /// Names of the flags that are set, in ffprobe naming
+        pub fn flags(&self) -> Vec<&'static str> {
+            return [
+                ("default", self.default),
+                ("dub", self.dub),
+                ("original", self.original),
+                ("comment", self.comment),
+                ("forced", self.forced),
+                ("hearing_impaired", self.hearing_impaired),
+                ("visual_impaired", self.visual_impaired),
+                ("attached_pic", self.attached_pic),
+                ("timed_thumbnails", self.timed_thumbnails),
+                ("captions", self.captions),
+                ("descriptions", self.descriptions),
+            ].into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(flag, _)| flag)
+            .collect();
+        }
+
+    }
+
+    /// ffprobe side data of a stream, only Dolby Vision configuration records are read.
+    #[derive(Deserialize)]
+    pub struct SideData {
+        side_data_type: Option<String>,
+        #[serde(default, deserialize_with = "number")]
+        dv_profile: Option<u8>,
+    }
+
+    impl SideData {
+
+        pub fn get_side_data_type(&self) -> Option<&String> {
+            return self.side_data_type.as_ref();
+        }
+
+        /// Dolby Vision profile of a DOVI configuration record
+        pub fn get_dolby_vision_profile(&self) -> Option<u8> {
+            return match &self.side_data_type {
+                Some(side_data_type) if side_data_type.starts_with("DOVI configuration record") => self.dv_profile,
+                _ => None,
+            };
+        }
+
+    }
+
+    /// Field orders of interlaced streams : top or bottom field first, coded in the same or the other order.
+    const INTERLACED_FIELD_ORDERS: [&str; 4] = ["tt", "bb", "tb", "bt"];
+    /// Largest relative gap between the average and the base frame rates of a constant frame rate stream,
+    /// muxers round the average frame rate of most constant frame rate medias.
+    const VARIABLE_FRAME_RATE_TOLERANCE: f64 = 0.001;
+
+    /// Frame rate written as a fraction by ffprobe, `0/0` when unknown.
+    fn frame_rate(fraction: Option<&String>) -> Option<f64> {
+        let (numerator, denominator) = fraction?.split_once('/')?;
+        let numerator: f64 = numerator.trim().parse().ok()?;
+        let denominator: f64 = denominator.trim().parse().ok()?;
+        return if numerator > 0.0 && denominator > 0.0 { Some(numerator / denominator) } else { None };
+    }
+
+    /// PQ transfer, used by HDR10, HDR10+ and most Dolby Vision base layers.
+    pub const PQ_TRANSFER: &str = "smpte2084";
+    /// Hybrid Log-Gamma transfer, used by broadcast HDR.
+    pub const HLG_TRANSFER: &str = "arib-std-b67";
+
+    /// One stream of a media, from the `streams` section of ffprobe.
+    #[derive(Deserialize)]
     pub struct Stream {
+        index: u32,
+        #[serde(rename = "codec_type")]
         codec: Option<Codec>,
+        #[serde(rename = "codec_name")]
         name: Option<String>,
-        language: Option<String>,
+        profile: Option<String>,
+        level: Option<i64>,
+        pix_fmt: Option<String>,
+        field_order: Option<String>,
+        r_frame_rate: Option<String>,
+        avg_frame_rate: Option<String>,
+        color_transfer: Option<String>,
+        color_primaries: Option<String>,
+        #[serde(default)]
+        side_data_list: Vec<SideData>,
+        width: Option<u32>,
+        height: Option<u32>,
+        channels: Option<u32>,
+        channel_layout: Option<String>,
+        #[serde(default, deserialize_with = "number")]
+        sample_rate: Option<u32>,
+        #[serde(default, deserialize_with = "number")]
+        bit_rate: Option<u64>,
+        #[serde(default, deserialize_with = "number")]
+        duration: Option<f64>,
+        #[serde(default)]
+        disposition: Disposition,
+        #[serde(default)]
+        tags: HashMap<String, String>,
     }
     
     impl Stream {
-    
-        pub fn new() -> Stream {
-            Stream {
-                codec: None,
-                name: None,
-                language: None,
-            }
+
+        pub fn get_index(&self) -> u32 {
+            return self.index;
         }
 
         pub fn get_codec(&self) -> Option<&Codec> {
             return self.codec.as_ref();
         }
 
-        pub fn set_codec(&mut self, codec: Codec) {
-            self.codec = Some(codec);
-        }
-
         pub fn get_name(&self) -> Option<&String> {
             return self.name.as_ref();
         }
 
-        pub fn set_name(&mut self, name: &str) {
-            self.name = Some(name.to_string());
+        pub fn get_profile(&self) -> Option<&String> {
+            return self.profile.as_ref();
         }
 
-        pub fn get_language(&self) -> Option<&String> {
-            return self.language.as_ref();
+        pub fn get_level(&self) -> Option<i64> {
+            return self.level;
         }
 
-        pub fn set_language(&mut self, language: &str) {
-            self.language = Some(language.to_string());
+        pub fn get_pix_fmt(&self) -> Option<&String> {
+            return self.pix_fmt.as_ref();
         }
-    
+
+        pub fn get_field_order(&self) -> Option<&String> {
+            return self.field_order.as_ref();
+        }
+
+        pub fn get_r_frame_rate(&self) -> Option<&String> {
+            return self.r_frame_rate.as_ref();
+        }
+
+        pub fn get_avg_frame_rate(&self) -> Option<&String> {
+            return self.avg_frame_rate.as_ref();
+        }
+
+        /// Base frame rate, the lowest rate all timestamps can be represented with
+        pub fn base_frame_rate(&self) -> Option<f64> {
+            return frame_rate(self.get_r_frame_rate());
+        }
+
+        pub fn average_frame_rate(&self) -> Option<f64> {
+            return frame_rate(self.get_avg_frame_rate());
+        }
+
+        /// Average frame rate, or the base frame rate when the average is unknown
+        pub fn frame_rate(&self) -> Option<f64> {
+            return self.average_frame_rate().or_else(|| self.base_frame_rate());
+        }
+
+        /// Progressive streams and streams of unknown field order are not interlaced.
+        pub fn is_interlaced(&self) -> bool {
+            return self.get_field_order().is_some_and(|field_order| INTERLACED_FIELD_ORDERS.contains(&field_order.as_str()));
+        }
+
+        /// A stream whose average frame rate is away from its base frame rate has frames of varying durations.
+        pub fn is_variable_frame_rate(&self) -> bool {
+            return match (self.base_frame_rate(), self.average_frame_rate()) {
+                (Some(base_frame_rate), Some(average_frame_rate)) => (base_frame_rate - average_frame_rate).abs() > base_frame_rate * VARIABLE_FRAME_RATE_TOLERANCE,
+                _ => false,
+            };
+        }
+
+        pub fn get_color_transfer(&self) -> Option<&String> {
+            return self.color_transfer.as_ref();
+        }
+
+        pub fn get_color_primaries(&self) -> Option<&String> {
+            return self.color_primaries.as_ref();
+        }
+
+        pub fn get_side_data(&self) -> &Vec<SideData> {
+            return &self.side_data_list;
+        }
+
+        pub fn is_hdr10(&self) -> bool {
+            return self.color_transfer.as_deref() == Some(PQ_TRANSFER);
+        }
+
+        pub fn is_hlg(&self) -> bool {
+            return self.color_transfer.as_deref() == Some(HLG_TRANSFER);
+        }
+
+        pub fn get_dolby_vision_profile(&self) -> Option<u8> {
+            return self.side_data_list.iter().find_map(SideData::get_dolby_vision_profile);
+        }
+
+        /// HDR formats of the stream, a Dolby Vision stream may also carry an HDR10 or HLG base layer.
+        pub fn hdr_formats(&self) -> Vec<String> {
+            let mut formats = Vec::new();
+            if let Some(dolby_vision_profile) = self.get_dolby_vision_profile() {
+                formats.push(format!("Dolby Vision profile {dolby_vision_profile}"));
+            }
+            if self.is_hdr10() {
+                formats.push("HDR10".to_string());
+            }
+            if self.is_hlg() {
+                formats.push("HLG".to_string());
+            }
+            return formats;
+        }
+
+        /// Bit depth per component, read from the digits following the last `p` of the pixel format
+        /// (`yuv420p10le` is 10 bits, `p010le` is 10 bits, `yuv420p` is 8 bits)
+        pub fn get_bit_depth(&self) -> Option<u32> {
+            return self.get_pix_fmt().map(|pix_fmt| {
+                let planar = pix_fmt.trim_end_matches("le").trim_end_matches("be");
+                return match planar.rfind('p') {
+                    Some(p_index) => planar[p_index + 1..].parse().unwrap_or(8),
+                    None => 8,
+                };
+            });
+        }
+
+        pub fn get_width(&self) -> Option<u32> {
+            return self.width;
+        }
+
+        pub fn get_height(&self) -> Option<u32> {
+            return self.height;
+        }
+
+        pub fn get_channels(&self) -> Option<u32> {
+            return self.channels;
+        }
+
+        pub fn get_channel_layout(&self) -> Option<&String> {
+            return self.channel_layout.as_ref();
+        }
+
+        pub fn get_sample_rate(&self) -> Option<u32> {
+            return self.sample_rate;
+        }
+
+        /// Bit rate reported by the stream, or computed by the muxer in the MKV `BPS` statistics tag
+        pub fn get_bit_rate(&self) -> Option<u64> {
+            return self.bit_rate.or_else(|| {
+                return self.get_tag("BPS")
+                .or_else(|| self.get_tag("BPS-eng"))
+                .and_then(|bit_rate| bit_rate.parse().ok());
+            });
+        }
+
+        pub fn get_duration(&self) -> Option<f64> {
+            return self.duration;
+        }
+
+        pub fn get_disposition(&self) -> &Disposition {
+            return &self.disposition;
+        }
+
+        pub fn get_tags(&self) -> &HashMap<String, String> {
+            return &self.tags;
+        }
+
+        /// Tag value, ffprobe keeps the case used by the muxer (`language` in MP4, `LANGUAGE` in some MKV)
+        pub fn get_tag(&self, key: &str) -> Option<&String> {
+            return self.tags.iter()
+            .find(|(tag, _)| tag.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value);
+        }
+
+        pub fn get_language(&self) -> Option<&String> {
+            return self.get_tag("language");
+        }
+
         pub fn is_video(&self) -> bool {
             return match self.get_codec() {
                 Some(codec) => Codec::Video.eq(codec),
@@ -202,34 +797,272 @@ pub mod streams {
             };
         }
     
-        pub fn is_filled(&self) -> bool {
-            return self.codec.is_some() && self.name.is_some() && (self.is_video() || self.language.is_some());
-        }
-    
-        pub fn is_valid_video_stream(&self) -> bool {
-            return self.is_video() && match self.get_name() {
-                Some(name) => "h264".eq(name),
+        fn is_image_attachment(&self) -> bool {
+            return match self.get_tag("mimetype") {
+                Some(mimetype) => mimetype.starts_with("image/"),
                 None => false,
             };
         }
-    
-        pub fn is_valid_audio_stream(&self) -> bool {
-            return self.is_audio() && match self.get_name() {
-                Some(name) => "aac".eq(name),
-                None => false,
+
+        pub fn class(&self) -> StreamClass {
+            return match self.get_codec() {
+                Some(Codec::Video) if self.disposition.is_attached_pic() => StreamClass::CoverArt,
+                Some(Codec::Video) if self.disposition.is_timed_thumbnails() => StreamClass::TimedThumbnails,
+                Some(Codec::Video) => StreamClass::Video,
+                Some(Codec::Audio) => StreamClass::Audio,
+                Some(Codec::Subtitle) => StreamClass::Subtitle,
+                Some(Codec::Attachment) if self.is_image_attachment() => StreamClass::CoverArt,
+                Some(Codec::Attachment) => StreamClass::Attachment,
+                Some(Codec::Data) => StreamClass::Data,
+                Some(Codec::Unknown) | None => StreamClass::Unknown,
             };
         }
-    
-        pub fn is_valid_subtitle_stream(&self) -> bool {
-            return self.is_subtitle() && match self.get_language() {
-                Some(language) => match language.as_str() {
-                    "fra" | "fre" | "eng" | "und" => true,
-                    _ => false,
+
+        pub fn get_type_name(&self) -> &'static str {
+            return self.class().name();
+        }
+
+        /// Stream designation used in messages : `audio #1`, `cover art #3`
+        pub fn label(&self) -> String {
+            return format!("{} #{}", self.get_type_name(), self.get_index());
+        }
+
+        fn codec_name(&self) -> &str {
+            return match self.get_name() {
+                Some(name) => name,
+                None => "unknown codec",
+            };
+        }
+
+        fn failure(&self, rule: Rule, message: String) -> Failure {
+            return Failure::of_stream(rule, self.get_index(), self.get_type_name(), format!("{} {message}", self.label()));
+        }
+
+        fn constraints_failures(&self, constraints: &CodecConstraints) -> Vec<Failure> {
+            let codec_name = self.codec_name();
+            let mut failures = Vec::new();
+            if !constraints.allows_profile(self.get_profile()) {
+                let stream_profile = self.get_profile().map_or("unknown", |profile| profile.as_str());
+                failures.push(self.failure(Rule::VideoProfile, format!("{codec_name} profile {stream_profile} not in {}", allowed(constraints.profiles()))));
+            }
+            if !constraints.allows_level(codec_name, self.get_level()) {
+                let stream_level = self.get_level().map_or("unknown".to_string(), |level| levels::written(codec_name, level));
+                let max_level = levels::written(codec_name, constraints.max_level(codec_name).unwrap_or_default());
+                failures.push(self.failure(Rule::VideoLevel, format!("{codec_name} level {stream_level} above {max_level}")));
+            }
+            if !constraints.allows_bit_depth(self.get_bit_depth()) {
+                let stream_bit_depth = self.get_bit_depth().map_or("unknown".to_string(), |bit_depth| bit_depth.to_string());
+                let max_bit_depth = constraints.max_bit_depth().unwrap_or_default();
+                failures.push(self.failure(Rule::VideoBitDepth, format!("{codec_name} {stream_bit_depth} bits above {max_bit_depth}")));
+            }
+            if !constraints.allows_pix_fmt(self.get_pix_fmt()) {
+                let stream_pix_fmt = self.get_pix_fmt().map_or("unknown", |pix_fmt| pix_fmt.as_str());
+                failures.push(self.failure(Rule::VideoPixelFormat, format!("{codec_name} pixel format {stream_pix_fmt} not in {}", allowed(constraints.pix_fmts()))));
+            }
+            return failures;
+        }
+
+        pub fn video_failures(&self, profile: &Profile) -> Vec<Failure> {
+            let video_rules = profile.video();
+            let mut failures = match self.get_name() {
+                Some(name) if video_rules.allows_codec(name) => match video_rules.constraints_for(name) {
+                    Some(constraints) => self.constraints_failures(constraints),
+                    None => Vec::new(),
+                },
+                _ => vec![self.failure(Rule::VideoCodec, format!("{} not in {}", self.codec_name(), allowed(video_rules.codecs())))],
+            };
+            failures.extend(self.dynamic_range_failures(video_rules));
+            failures.extend(self.scan_failures(video_rules));
+            failures.extend(self.downscale_failures(video_rules));
+            return failures;
+        }
+
+        /// Streams over the resolution or frame rate ceilings play once downscaled, whatever their codec.
+        fn downscale_failures(&self, video_rules: &VideoRules) -> Vec<Failure> {
+            let codec_name = self.codec_name();
+            let mut failures = Vec::new();
+            if !video_rules.allows_resolution(self.get_width(), self.get_height()) {
+                let written = |dimension: Option<u32>| dimension.map_or("any".to_string(), |dimension| dimension.to_string());
+                failures.push(self.failure(Rule::VideoDownscale, format!(
+                    "{codec_name} {}x{} above {}x{}, downscale required",
+                    written(self.get_width()), written(self.get_height()), written(video_rules.max_width()), written(video_rules.max_height()),
+                )));
+            }
+            if !video_rules.allows_frame_rate(self.frame_rate()) {
+                failures.push(self.failure(Rule::VideoDownscale, format!(
+                    "{codec_name} {:.3} fps above {} fps, downscale required",
+                    self.frame_rate().unwrap_or_default(), video_rules.max_frame_rate().unwrap_or_default(),
+                )));
+            }
+            return failures;
+        }
+
+        /// Interlaced and variable frame rate streams stutter on players that do not deinterlace or resample them.
+        fn scan_failures(&self, video_rules: &VideoRules) -> Vec<Failure> {
+            let codec_name = self.codec_name();
+            let mut failures = Vec::new();
+            if self.is_interlaced() && !video_rules.allows_interlaced() {
+                let field_order = self.get_field_order().map_or("", |field_order| field_order.as_str());
+                failures.push(self.failure(Rule::VideoInterlaced, format!("{codec_name} interlaced ({field_order} field order), deinterlace required")));
+            }
+            if self.is_variable_frame_rate() && !video_rules.allows_variable_frame_rate() {
+                failures.push(self.failure(Rule::VideoVariableFrameRate, format!(
+                    "{codec_name} variable frame rate (average {:.3} fps, base {:.3} fps), constant frame rate required",
+                    self.average_frame_rate().unwrap_or_default(), self.base_frame_rate().unwrap_or_default(),
+                )));
+            }
+            return failures;
+        }
+
+        /// HDR streams need tone-mapping on SDR players, even when their codec is allowed.
+        fn dynamic_range_failures(&self, video_rules: &VideoRules) -> Vec<Failure> {
+            let codec_name = self.codec_name();
+            let mut failures = Vec::new();
+            if let Some(dolby_vision_profile) = self.get_dolby_vision_profile().filter(|dv_profile| !video_rules.allows_dolby_vision_profile(*dv_profile)) {
+                failures.push(self.failure(Rule::VideoDolbyVision, format!("{codec_name} Dolby Vision profile {dolby_vision_profile} not supported")));
+            }
+            if self.is_hdr10() && !video_rules.allows_hdr10() {
+                failures.push(self.failure(Rule::VideoHdr10, format!("{codec_name} HDR10 ({PQ_TRANSFER} transfer) not supported")));
+            }
+            if self.is_hlg() && !video_rules.allows_hlg() {
+                failures.push(self.failure(Rule::VideoHlg, format!("{codec_name} HLG ({HLG_TRANSFER} transfer) not supported")));
+            }
+            return failures;
+        }
+
+        fn audio_constraints_failures(&self, constraints: &AudioConstraints) -> Vec<Failure> {
+            let codec_name = self.codec_name();
+            let mut failures = Vec::new();
+            if !constraints.allows_profile(self.get_profile()) {
+                let stream_profile = self.get_profile().map_or("unknown", |profile| profile.as_str());
+                failures.push(self.failure(Rule::AudioProfile, format!("{codec_name} profile {stream_profile} not in {}", allowed(constraints.profiles()))));
+            }
+            if !constraints.allows_channels(self.get_channels()) {
+                let stream_channels = self.get_channels().map_or("unknown".to_string(), |channels| channels.to_string());
+                let max_channels = constraints.max_channels().unwrap_or_default();
+                failures.push(self.failure(Rule::AudioChannels, format!("{codec_name} {stream_channels} channels above {max_channels}")));
+            }
+            if !constraints.allows_channel_layout(self.get_channel_layout()) {
+                let stream_layout = self.get_channel_layout().map_or("unknown", |layout| layout.as_str());
+                failures.push(self.failure(Rule::AudioChannelLayout, format!("{codec_name} channel layout {stream_layout} not in {}", allowed(constraints.channel_layouts()))));
+            }
+            if !constraints.allows_sample_rate(self.get_sample_rate()) {
+                let stream_sample_rate = self.get_sample_rate().map_or("unknown".to_string(), |sample_rate| sample_rate.to_string());
+                let sample_rates: Vec<String> = constraints.sample_rates().iter().map(u32::to_string).collect();
+                failures.push(self.failure(Rule::AudioSampleRate, format!("{codec_name} sample rate {stream_sample_rate} not in {}", allowed(&sample_rates))));
+            }
+            if let Some(bit_rate) = self.get_bit_rate().filter(|bit_rate| !constraints.allows_bit_rate(Some(*bit_rate))) {
+                let message = match (constraints.min_bit_rate(), constraints.max_bit_rate()) {
+                    (Some(min_bit_rate), _) if bit_rate < min_bit_rate => format!("{codec_name} bit rate {bit_rate} below {min_bit_rate}"),
+                    (_, max_bit_rate) => format!("{codec_name} bit rate {bit_rate} above {}", max_bit_rate.unwrap_or_default()),
+                };
+                failures.push(self.failure(Rule::AudioBitRate, message));
+            }
+            return failures;
+        }
+
+        pub fn audio_failures(&self, profile: &Profile) -> Vec<Failure> {
+            let audio_rules = profile.audio();
+            return match self.get_name() {
+                Some(name) if audio_rules.allows_codec(name) => match audio_rules.constraints_for(name) {
+                    Some(constraints) => self.audio_constraints_failures(constraints),
+                    None => Vec::new(),
                 },
-                None => false
+                _ => vec![self.failure(Rule::AudioCodec, format!("{} not in {}", self.codec_name(), allowed(audio_rules.codecs())))],
+            };
+        }
+
+        pub fn subtitle_failures(&self, profile: &Profile) -> Vec<Failure> {
+            let subtitle_rules = profile.subtitle();
+            let language = match self.get_language() {
+                Some(language) => language.as_str(),
+                None => UNDETERMINED_LANGUAGE,
+            };
+            let mut failures = Vec::new();
+            if !subtitle_rules.allows_language(language) {
+                failures.push(self.failure(Rule::SubtitleLanguage, format!("language {language} not in {}", allowed(subtitle_rules.languages()))));
+            }
+            let kind = self.subtitle_kind();
+            if !subtitle_rules.allows_kind(kind) {
+                failures.push(self.failure(Rule::SubtitleKind, format!("{} {} subtitles not allowed", self.codec_name(), kind.name())));
+            }
+            return failures;
+        }
+
+        /// Unknown for streams that are not subtitles
+        pub fn subtitle_kind(&self) -> SubtitleKind {
+            return match self.get_name() {
+                Some(codec_name) if self.is_subtitle() => SubtitleKind::of(codec_name),
+                _ => SubtitleKind::Unknown,
+            };
+        }
+
+        fn extra_stream_failures(&self, rule: Rule, rules: &ExtraStreamRules) -> Vec<Failure> {
+            return if !rules.is_allowed() {
+                vec![self.failure(rule, format!("{} not allowed", self.codec_name()))]
+            } else if !rules.allows_codec(self.get_name()) {
+                vec![self.failure(rule, format!("{} not in {}", self.codec_name(), allowed(rules.codecs())))]
+            } else {
+                Vec::new()
+            };
+        }
+
+        /// Every rule of the profile this stream does not follow, streams of unknown type are not checked.
+        pub fn failures(&self, profile: &Profile) -> Vec<Failure> {
+            return match self.class() {
+                StreamClass::Video => self.video_failures(profile),
+                StreamClass::Audio => self.audio_failures(profile),
+                StreamClass::Subtitle => self.subtitle_failures(profile),
+                StreamClass::CoverArt => self.extra_stream_failures(Rule::CoverArt, profile.cover_art()),
+                StreamClass::TimedThumbnails => self.extra_stream_failures(Rule::TimedThumbnails, profile.timed_thumbnails()),
+                StreamClass::Attachment => self.extra_stream_failures(Rule::Attachment, profile.attachment()),
+                StreamClass::Data => self.extra_stream_failures(Rule::Data, profile.data()),
+                StreamClass::Unknown => Vec::new(),
             };
         }
+
+        pub fn is_valid_video_stream(&self, profile: &Profile) -> bool {
+            return StreamClass::Video.eq(&self.class()) && self.video_failures(profile).is_empty();
+        }
     
+        pub fn is_valid_audio_stream(&self, profile: &Profile) -> bool {
+            return self.is_audio() && self.audio_failures(profile).is_empty();
+        }
+    
+        pub fn is_valid_subtitle_stream(&self, profile: &Profile) -> bool {
+            return self.is_subtitle() && self.subtitle_failures(profile).is_empty();
+        }
+    
+    }
+
+    fn language_or_undetermined(stream: &Stream) -> &str {
+        return match stream.get_language() {
+            Some(language) => language.as_str(),
+            None => UNDETERMINED_LANGUAGE,
+        };
+    }
+
+    /// Audio languages the media lacks : no audio stream in the profile languages, or a default audio
+    /// stream in another language. Players start the first audio stream when none is marked as default.
+    pub fn audio_language_failures(streams: &[Stream], profile: &Profile) -> Vec<Failure> {
+        let audio_rules = profile.audio();
+        if !audio_rules.requires_language() {
+            return Vec::new();
+        }
+        let audio_streams: Vec<&Stream> = streams.iter().filter(|stream| StreamClass::Audio.eq(&stream.class())).collect();
+        let languages = allowed(audio_rules.languages());
+        if !audio_streams.iter().any(|stream| audio_rules.allows_language(language_or_undetermined(stream))) {
+            let found: Vec<String> = audio_streams.iter().map(|stream| language_or_undetermined(stream).to_string()).collect();
+            return vec![Failure::of_media(Rule::AudioLanguage, format!("no audio in {languages}, audio languages {}", allowed(&found)))];
+        }
+        let default_audio = audio_streams.iter().find(|stream| stream.get_disposition().is_default()).or(audio_streams.first());
+        return match default_audio {
+            Some(stream) if audio_rules.requires_default_language() && !audio_rules.allows_language(language_or_undetermined(stream)) => {
+                let message = format!("default, language {} not in {languages}", language_or_undetermined(stream));
+                vec![stream.failure(Rule::DefaultAudioLanguage, message)]
+            },
+            _ => Vec::new(),
+        };
     }
 
 }
\ No newline at end of file
@@ -1,83 +1,107 @@
+use crate::errors::{ErrorLog, LadonError};
 use crate::utils::validation::validate_directory;
 use crate::utils::path::{directory_entries, location_of, type_of};
 use crate::utils::file::{file_name, file_directory, without_ext, delete, create_backup};
-use crate::utils::media::is_media;
+use crate::utils::media::MediaDetector;
 use crate::terminal::{Terminal, ImportTerm};
 use std::collections::HashMap;
-use std::fs::copy;
-use itertools::Itertools;
+use std::ffi::OsString;
+use std::fs::{copy, rename};
+use std::path::{Path, PathBuf};
 
-fn origin(media: &String, medias_map: &mut HashMap<String, String>) -> String {
-    return match medias_map.get(media) {
-        Some(location) => location.to_string(),
-        None => panic!("No path for media '{media}'!")
-    };
-}
-
-fn destination(origin: &str, target: &str) -> String {
-    let target_directory = file_directory(target);
-    let origin_media_name = file_name(&origin);
-    return format!("{target_directory}/{origin_media_name}");
+fn destination(origin: &Path, target: &Path) -> PathBuf {
+    return file_directory(target).join(file_name(origin));
 }
 
-fn import(origin: &str, target: &str) {
+/// The library media is put back when the transcoded one cannot be copied,
+/// a backup that cannot be put back is logged since the copy error is the one handed back.
+fn import(origin: &Path, target: &Path) -> Result<(), LadonError> {
     let destination = &destination(origin, target);
-    let target_backup = create_backup(target);
-    match copy(origin, destination){
+    let target_backup = create_backup(target)?;
+    return match copy(origin, destination) {
         Ok(_) => delete(&target_backup),
-        Err(e) => panic!("Error moving {origin} to {destination}: {e:?}")
-    }
+        Err(e) => {
+            if let Err(restore_error) = rename(&target_backup, target) {
+                let restore_error = LadonError::io("restoring", target, restore_error);
+                eprintln!("Error: {restore_error}, the library media is left at {}", target_backup.display());
+            }
+            Err(LadonError::io("copying", origin, e))
+        },
+    };
 }
 
-fn process_media(existing_media_location: &str, medias_map: &mut HashMap<String, String>, importer_terminal: &mut Terminal) {
-    let existing_media_name = file_name(existing_media_location);
-    let existing_media = without_ext(existing_media_name).to_string();
-    if medias_map.contains_key(&existing_media) {
-        let origin = &origin(&existing_media, medias_map);
-        importer_terminal.update_import(origin);
-        import(origin, existing_media_location);
-        medias_map.remove(&existing_media);
+fn process_media(existing_media_location: &Path, medias_map: &mut HashMap<OsString, PathBuf>, importer_terminal: &mut Terminal, errors: &ErrorLog) -> Result<(), LadonError> {
+    let existing_media = without_ext(file_name(existing_media_location));
+    if let Some(origin) = medias_map.remove(existing_media) {
+        importer_terminal.update_import(&origin.display().to_string());
+        errors.skip(import(&origin, existing_media_location))?;
         importer_terminal.searching();
     }
+    return Ok(());
 }
 
-fn search_recursively(location: &str, medias_map: &mut HashMap<String, String>, importer_terminal: &mut Terminal) {
-    for entry in directory_entries(location) {
+fn search_recursively(location: &Path, medias_map: &mut HashMap<OsString, PathBuf>, importer_terminal: &mut Terminal, detector: &MediaDetector, errors: &ErrorLog) -> Result<(), LadonError> {
+    let entries = match errors.skip(directory_entries(location))? {
+        Some(entries) => entries,
+        None => return Ok(()),
+    };
+    for entry in entries {
         if(medias_map.is_empty()) { break }
+        let entry = match errors.skip(entry)? {
+            Some(entry) => entry,
+            None => continue,
+        };
         let entry_location = location_of(&entry);
-        let entry_type = type_of(&entry, &entry_location);
+        let entry_type = match errors.skip(type_of(&entry, &entry_location))? {
+            Some(entry_type) => entry_type,
+            None => continue,
+        };
         if entry_type.is_dir() {
-            search_recursively(&entry_location, medias_map, importer_terminal)
-        } else if is_media(&entry_location) {
-            process_media(&entry_location, medias_map, importer_terminal);
+            search_recursively(&entry_location, medias_map, importer_terminal, detector, errors)?;
+        } else if detector.is_media(&entry_location) {
+            process_media(&entry_location, medias_map, importer_terminal, errors)?;
         }
     }
+    return Ok(());
 }
 
-fn get_medias_map(input_directory_location: &str) -> HashMap<String, String> {
-    return directory_entries(input_directory_location)
-    .map(|entry| location_of(&entry))
-    .filter(|file_location| is_media(file_location))
-    .map(|media_location| (without_ext(file_name(&media_location)).to_string(), media_location))
-    .collect();
+/// Transcoded medias by file name without extension, compared as the OS gives them.
+fn get_medias_map(input_directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog) -> Result<HashMap<OsString, PathBuf>, LadonError> {
+    let mut medias_map = HashMap::new();
+    for entry in directory_entries(input_directory_location)? {
+        if let Some(entry) = errors.skip(entry)? {
+            let media_location = location_of(&entry);
+            if detector.is_media(&media_location) {
+                medias_map.insert(without_ext(file_name(&media_location)).to_os_string(), media_location);
+            }
+        }
+    }
+    return Ok(medias_map);
 }
 
-fn fail_import(medias_map: &HashMap<String, String>) {
-    let non_imported_medias = medias_map.values().join("\n");
-    panic!("Import failed!\nRemaining medias not imported :\n{non_imported_medias}");
+/// Every transcoded media left is an error, sorted so that the first one does not depend on the map order.
+fn fail_import(medias_map: HashMap<OsString, PathBuf>, errors: &ErrorLog) -> Result<(), LadonError> {
+    let mut non_imported_medias: Vec<PathBuf> = medias_map.into_values().collect();
+    non_imported_medias.sort();
+    for media_location in non_imported_medias {
+        errors.keep(LadonError::NotImported(media_location))?;
+    }
+    return Ok(());
 }
 
-fn process_medias_import(input_directory_location: &str, target_directory_location: &str) {
-    let mut medias_map: HashMap<String, String> = get_medias_map(input_directory_location);
-    let mut importer_terminal: Terminal = ImportTerm::new(medias_map.len(), target_directory_location);
+fn process_medias_import(input_directory_location: &Path, target_directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog) -> Result<(), LadonError> {
+    let mut medias_map: HashMap<OsString, PathBuf> = get_medias_map(input_directory_location, detector, errors)?;
+    let target_directory_display = target_directory_location.display().to_string();
+    let mut importer_terminal: Terminal = ImportTerm::new(medias_map.len(), &target_directory_display);
     importer_terminal.import_start();
-    search_recursively(target_directory_location, &mut medias_map, &mut importer_terminal);
-    if(!medias_map.is_empty()) { fail_import(&medias_map) }
+    search_recursively(target_directory_location, &mut medias_map, &mut importer_terminal, detector, errors)?;
     importer_terminal.import_done();
+    return fail_import(medias_map, errors);
 }
 
-pub fn import_medias(input_directory_location: &str, target_directory_location: &str) {
-    validate_directory(input_directory_location);
-    validate_directory(target_directory_location);
-    process_medias_import(input_directory_location, target_directory_location);
-}
\ No newline at end of file
+/// Transcoded and library medias are told apart from other files like `search` does.
+pub fn import_medias(input_directory_location: &Path, target_directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog) -> Result<(), LadonError> {
+    validate_directory(input_directory_location)?;
+    validate_directory(target_directory_location)?;
+    return process_medias_import(input_directory_location, target_directory_location, detector, errors);
+}
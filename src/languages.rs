@@ -0,0 +1,111 @@
+/// One language with all the tags media files use for it.
+struct Language {
+    /// ISO 639-2/T code, the form every tag is normalized to
+    terminology: &'static str,
+    /// ISO 639-2/B code, the same as the terminology code for most languages
+    bibliographic: &'static str,
+    /// ISO 639-1 code, empty when the language has none
+    alpha2: &'static str,
+    /// English names, lowercase
+    names: &'static [&'static str],
+}
+
+const fn language(terminology: &'static str, bibliographic: &'static str, alpha2: &'static str, names: &'static [&'static str]) -> Language {
+    Language { terminology, bibliographic, alpha2, names }
+}
+
+const LANGUAGES: &[Language] = &[
+    language("ara", "ara", "ar", &["arabic"]),
+    language("bul", "bul", "bg", &["bulgarian"]),
+    language("cat", "cat", "ca", &["catalan"]),
+    language("ces", "cze", "cs", &["czech"]),
+    language("cym", "wel", "cy", &["welsh"]),
+    language("dan", "dan", "da", &["danish"]),
+    language("deu", "ger", "de", &["german"]),
+    language("ell", "gre", "el", &["greek", "modern greek"]),
+    language("eng", "eng", "en", &["english"]),
+    language("est", "est", "et", &["estonian"]),
+    language("eus", "baq", "eu", &["basque"]),
+    language("fas", "per", "fa", &["persian", "farsi"]),
+    language("fin", "fin", "fi", &["finnish"]),
+    language("fra", "fre", "fr", &["french"]),
+    language("gle", "gle", "ga", &["irish"]),
+    language("glg", "glg", "gl", &["galician"]),
+    language("heb", "heb", "he", &["hebrew"]),
+    language("hin", "hin", "hi", &["hindi"]),
+    language("hrv", "hrv", "hr", &["croatian"]),
+    language("hun", "hun", "hu", &["hungarian"]),
+    language("hye", "arm", "hy", &["armenian"]),
+    language("ind", "ind", "id", &["indonesian"]),
+    language("isl", "ice", "is", &["icelandic"]),
+    language("ita", "ita", "it", &["italian"]),
+    language("jpn", "jpn", "ja", &["japanese"]),
+    language("kat", "geo", "ka", &["georgian"]),
+    language("kor", "kor", "ko", &["korean"]),
+    language("lat", "lat", "la", &["latin"]),
+    language("lav", "lav", "lv", &["latvian"]),
+    language("lit", "lit", "lt", &["lithuanian"]),
+    language("mkd", "mac", "mk", &["macedonian"]),
+    language("msa", "may", "ms", &["malay"]),
+    language("nld", "dut", "nl", &["dutch", "flemish"]),
+    language("nor", "nor", "no", &["norwegian"]),
+    language("nob", "nob", "nb", &["norwegian bokmal", "norwegian bokmål", "bokmal", "bokmål"]),
+    language("nno", "nno", "nn", &["norwegian nynorsk", "nynorsk"]),
+    language("pol", "pol", "pl", &["polish"]),
+    language("por", "por", "pt", &["portuguese"]),
+    language("ron", "rum", "ro", &["romanian", "moldavian"]),
+    language("rus", "rus", "ru", &["russian"]),
+    language("slk", "slo", "sk", &["slovak"]),
+    language("slv", "slv", "sl", &["slovenian", "slovene"]),
+    language("spa", "spa", "es", &["spanish", "castilian"]),
+    language("sqi", "alb", "sq", &["albanian"]),
+    language("srp", "srp", "sr", &["serbian"]),
+    language("swe", "swe", "sv", &["swedish"]),
+    language("tam", "tam", "ta", &["tamil"]),
+    language("tel", "tel", "te", &["telugu"]),
+    language("tha", "tha", "th", &["thai"]),
+    language("tur", "tur", "tr", &["turkish"]),
+    language("ukr", "ukr", "uk", &["ukrainian"]),
+    language("urd", "urd", "ur", &["urdu"]),
+    language("vie", "vie", "vi", &["vietnamese"]),
+    language("zho", "chi", "zh", &["chinese", "mandarin"]),
+    language("yue", "yue", "", &["cantonese"]),
+    language("mul", "mul", "", &["multiple languages", "multiple"]),
+    language("zxx", "zxx", "", &["no linguistic content"]),
+    language("und", "und", "", &["undetermined", "undefined", "unknown"]),
+];
+
+/// Tag without its region or script subtag : `fr-FR`, `pt_BR` and `zh-Hans` are `fr`, `pt` and `zh`.
+fn primary_subtag(tag: &str) -> String {
+    let tag = tag.trim();
+    let primary = match tag.find(['-', '_']) {
+        Some(separator) if separator > 0 => &tag[..separator],
+        _ => tag,
+    };
+    return primary.to_lowercase();
+}
+
+fn find_language(tag: &str) -> Option<&'static Language> {
+    let full_tag = tag.trim().to_lowercase();
+    let primary = primary_subtag(tag);
+    return LANGUAGES.iter().find(|language| language.names.contains(&full_tag.as_str()))
+    .or_else(|| LANGUAGES.iter().find(|language| {
+        primary.eq(language.terminology) || primary.eq(language.bibliographic)
+        || (!language.alpha2.is_empty() && primary.eq(language.alpha2))
+        || language.names.contains(&primary.as_str())
+    }));
+}
+
+/// ISO 639-2/T code of a language tag written as an ISO 639-1, 639-2/B or 639-2/T code, an English name
+/// or a locale like `fr-FR`. None when the language is not known.
+pub fn normalize(tag: &str) -> Option<&'static str> {
+    return find_language(tag).map(|language| language.terminology);
+}
+
+/// Whether two tags name the same language, unknown tags are compared without their case.
+pub fn same_language(tag: &str, other_tag: &str) -> bool {
+    return match (normalize(tag), normalize(other_tag)) {
+        (Some(language), Some(other_language)) => language.eq(other_language),
+        _ => tag.trim().eq_ignore_ascii_case(other_tag.trim()),
+    };
+}
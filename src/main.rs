@@ -1,4 +1,5 @@
 #![allow(unused)]
+#![allow(clippy::needless_return)]
 
 mod utils;
 mod terminal;
@@ -6,52 +7,267 @@ mod ffprobe;
 mod search;
 mod export;
 mod import;
+mod profile;
+mod targets;
+mod cache;
+mod failures;
+mod report;
+mod probe;
+mod container;
+mod errors;
+mod ffmpeg;
+mod verify;
+mod languages;
+mod savings;
+mod stats;
 
 use clap::Parser;
-use search::search_medias_to_transcode;
+use search::{search_medias_to_transcode, SearchOptions, Mode, Order};
 use export::export_medias;
 use import::import_medias;
+use probe::probe_media;
+use verify::{verify_medias, VerifyOptions};
+use stats::{print_library_stats, StatsOptions};
+use ffmpeg::decoders::{Decoder, FfmpegCli, FixtureDecoder};
+use ffprobe::probers::{Prober, FfprobeCli, FixtureProber};
+use cache::default_cache_location;
+use utils::media::{default_extensions, MediaDetector};
+use utils::list::ListFormat;
+use errors::{ErrorLog, LadonError};
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[clap(version)]
 struct Args {
     #[clap(subcommand)]
     action: Actions,
+    /// Seconds given to ffprobe to read a media before it is skipped
+    #[clap(long, global = true, value_parser, default_value_t = 60)]
+    probe_timeout: u64,
+    /// Log the files that cannot be processed and go on, a summary of them ends the run
+    #[clap(long, global = true)]
+    keep_going: bool,
+    /// Replay recorded ffprobe and ffmpeg outputs from this directory instead of running them
+    #[clap(long, global = true, hide = true, value_parser)]
+    probe_fixtures: Option<String>,
 }
 
 #[derive(clap::Subcommand)]
 enum Actions {
     Search {
         #[clap(short, long, value_parser)]
-        media_directory: String,
+        media_directory: PathBuf,
         #[clap(short, long, value_parser)]
-        output_file: String
+        output_file: PathBuf,
+        /// TOML compatibility profile, built-in rules are used when omitted
+        #[clap(short, long, value_parser)]
+        profile: Option<String>,
+        /// Built-in device profile, only files this device cannot direct-play are recorded
+        #[clap(short, long, conflicts_with = "profile", value_parser = clap::builder::PossibleValuesParser::new(targets::TARGET_NAMES))]
+        target: Option<String>,
+        /// Number of medias probed at the same time
+        #[clap(short, long, value_parser = clap::value_parser!(usize), default_value_t = 1)]
+        jobs: usize,
+        /// Medias to record : incompatible ones, or wastefully large ones ranked by estimated savings
+        #[clap(long, value_enum, default_value_t = Mode::Compatibility)]
+        mode: Mode,
+        /// Order of the medias in the output file, space saving searches are ranked by savings
+        #[clap(long, value_enum, default_value_t = Order::Path)]
+        order: Order,
+        /// Probe cache file, defaults to $XDG_CACHE_HOME/ladon/probes.json
+        #[clap(long, value_parser)]
+        cache: Option<String>,
+        /// Probe every media without reading or writing the probe cache
+        #[clap(long, conflicts_with_all = ["cache", "rebuild_cache"])]
+        no_cache: bool,
+        /// Forget the cached probes and probe every media again
+        #[clap(long)]
+        rebuild_cache: bool,
+        /// Comma separated extensions of the medias to check, whatever their case
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also check files recognized as medias from their first bytes, whatever their extension
+        #[clap(long)]
+        sniff: bool,
+        /// Format of the output file, `nul` separates raw paths with NUL bytes
+        #[clap(long, value_enum, default_value_t = ListFormat::Escaped)]
+        list_format: ListFormat
     },
     Export {
         #[clap(short, long, value_parser)]
-        medias_list: String,
+        medias_list: PathBuf,
         #[clap(short, long, value_parser)]
-        export_directory: String
+        export_directory: PathBuf,
+        /// Format of the medias list, as written by `search`
+        #[clap(long, value_enum, default_value_t = ListFormat::Escaped)]
+        list_format: ListFormat,
+        /// Comma separated extensions of the medias to export, the ones given to `search`
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also export files recognized as medias from their first bytes, for lists written by `search --sniff`
+        #[clap(long)]
+        sniff: bool
     },
     Import {
         #[clap(short, long, value_parser)]
-        input_directory: String,
+        input_directory: PathBuf,
+        #[clap(short, long, value_parser)]
+        target_directory: PathBuf,
+        /// Comma separated extensions of the medias to import and replace, whatever their case
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also import and replace files recognized as medias from their first bytes, whatever their extension
+        #[clap(long)]
+        sniff: bool
+    },
+    /// List the medias that are truncated or do not decode cleanly
+    Verify {
+        #[clap(short, long, value_parser)]
+        media_directory: PathBuf,
+        #[clap(short, long, value_parser)]
+        output_file: PathBuf,
+        /// Only check that medias can be probed and are not truncated, without decoding them with ffmpeg
+        #[clap(long)]
+        quick: bool,
+        /// Seconds given to ffmpeg to decode a media before it fails
+        #[clap(long, value_parser, default_value_t = 3600)]
+        decode_timeout: u64,
+        /// Number of medias verified at the same time
+        #[clap(short, long, value_parser = clap::value_parser!(usize), default_value_t = 1)]
+        jobs: usize,
+        /// Comma separated extensions of the medias to verify, whatever their case
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also verify files recognized as medias from their first bytes, whatever their extension
+        #[clap(long)]
+        sniff: bool,
+        /// Format of the output file, `nul` separates raw paths with NUL bytes
+        #[clap(long, value_enum, default_value_t = ListFormat::Escaped)]
+        list_format: ListFormat
+    },
+    /// Print histograms of the codecs, containers, resolutions, subtitle languages and dynamic ranges of a library
+    Stats {
+        #[clap(value_parser)]
+        media_directory: PathBuf,
+        /// TOML compatibility profile the medias are counted against, built-in rules are used when omitted
         #[clap(short, long, value_parser)]
-        target_directory: String
+        profile: Option<String>,
+        /// Built-in device profile the medias are counted against
+        #[clap(short, long, conflicts_with = "profile", value_parser = clap::builder::PossibleValuesParser::new(targets::TARGET_NAMES))]
+        target: Option<String>,
+        /// Number of medias probed at the same time
+        #[clap(short, long, value_parser = clap::value_parser!(usize), default_value_t = 1)]
+        jobs: usize,
+        /// Probe cache file, defaults to $XDG_CACHE_HOME/ladon/probes.json
+        #[clap(long, value_parser)]
+        cache: Option<String>,
+        /// Probe every media without reading or writing the probe cache
+        #[clap(long, conflicts_with_all = ["cache", "rebuild_cache"])]
+        no_cache: bool,
+        /// Forget the cached probes and probe every media again
+        #[clap(long)]
+        rebuild_cache: bool,
+        /// Comma separated extensions of the medias to count, whatever their case
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also count files recognized as medias from their first bytes, whatever their extension
+        #[clap(long)]
+        sniff: bool
+    },
+    /// Show the streams of a media and why it needs transcoding
+    #[clap(alias = "explain")]
+    Probe {
+        #[clap(value_parser)]
+        media_file: PathBuf,
+        /// TOML compatibility profile, built-in rules are used when omitted
+        #[clap(short, long, value_parser)]
+        profile: Option<String>,
+        /// Built-in device profile
+        #[clap(short, long, conflicts_with = "profile", value_parser = clap::builder::PossibleValuesParser::new(targets::TARGET_NAMES))]
+        target: Option<String>,
+        /// Comma separated extensions of the medias to explain, the ones given to `search`
+        #[clap(long, value_delimiter = ',', default_values_t = default_extensions())]
+        extensions: Vec<String>,
+        /// Also explain files recognized as medias from their first bytes, for medias found by `search --sniff`
+        #[clap(long)]
+        sniff: bool
     }
 }
 
-fn main() {
-    let args = Args::parse();
-    match &args.action {
-        Actions::Search { media_directory, output_file } => {
-            search_medias_to_transcode(media_directory, output_file)
+fn prober(args: &Args) -> Box<dyn Prober> {
+    return match &args.probe_fixtures {
+        Some(fixtures_directory) => Box::new(FixtureProber::new(fixtures_directory)),
+        None => Box::new(FfprobeCli::new(Duration::from_secs(args.probe_timeout))),
+    };
+}
+
+fn decoder(args: &Args, decode_timeout: u64) -> Box<dyn Decoder> {
+    return match &args.probe_fixtures {
+        Some(fixtures_directory) => Box::new(FixtureDecoder::new(fixtures_directory)),
+        None => Box::new(FfmpegCli::new(Duration::from_secs(decode_timeout))),
+    };
+}
+
+fn run(args: &Args, prober: &dyn Prober, errors: &ErrorLog) -> Result<(), LadonError> {
+    return match &args.action {
+        Actions::Search { media_directory, output_file, profile, target, jobs, mode, order, cache, no_cache, rebuild_cache, extensions, sniff, list_format } => {
+            let options = SearchOptions {
+                profile_location: profile.as_deref(),
+                target_name: target.as_deref(),
+                jobs: *jobs,
+                mode: mode.clone(),
+                order: order.clone(),
+                cache_location: if *no_cache { None } else { cache.clone().or_else(default_cache_location) },
+                rebuild_cache: *rebuild_cache,
+                extensions: extensions.clone(),
+                sniff: *sniff,
+                list_format: *list_format,
+            };
+            search_medias_to_transcode(media_directory, output_file, options, prober, errors)
+        },
+        Actions::Export { medias_list, export_directory, list_format, extensions, sniff } => {
+            export_medias(medias_list, *list_format, export_directory, &MediaDetector::new(extensions.clone(), *sniff), errors)
+        },
+        Actions::Import { input_directory, target_directory, extensions, sniff } => {
+            import_medias(input_directory, target_directory, &MediaDetector::new(extensions.clone(), *sniff), errors)
         },
-        Actions::Export { medias_list, export_directory } => {
-            export_medias(medias_list, export_directory)
+        Actions::Verify { media_directory, output_file, quick, decode_timeout, jobs, extensions, sniff, list_format } => {
+            let options = VerifyOptions {
+                jobs: *jobs,
+                quick: *quick,
+                extensions: extensions.clone(),
+                sniff: *sniff,
+                list_format: *list_format,
+            };
+            verify_medias(media_directory, output_file, options, prober, decoder(args, *decode_timeout).as_ref(), errors)
         },
-        Actions::Import { input_directory, target_directory } => {
-            import_medias(input_directory, target_directory)
+        Actions::Stats { media_directory, profile, target, jobs, cache, no_cache, rebuild_cache, extensions, sniff } => {
+            let options = StatsOptions {
+                profile_location: profile.as_deref(),
+                target_name: target.as_deref(),
+                jobs: *jobs,
+                cache_location: if *no_cache { None } else { cache.clone().or_else(default_cache_location) },
+                rebuild_cache: *rebuild_cache,
+                extensions: extensions.clone(),
+                sniff: *sniff,
+            };
+            print_library_stats(media_directory, options, prober, errors)
         },
+        Actions::Probe { media_file, profile, target, extensions, sniff } => {
+            probe_media(media_file, profile.as_deref(), target.as_deref(), &MediaDetector::new(extensions.clone(), *sniff), prober)
+        },
+    };
+}
+
+fn main() {
+    let args = Args::parse();
+    let prober = prober(&args);
+    let errors = ErrorLog::new(args.keep_going);
+    let result = run(&args, prober.as_ref(), &errors);
+    if let Err(e) = result.and(errors.summary()) {
+        eprintln!("Error: {e}");
+        exit(e.exit_code());
     }
 }
@@ -0,0 +1,93 @@
+use crate::errors::LadonError;
+use crate::ffprobe::Probe;
+use crate::ffprobe::probers::Prober;
+use crate::ffprobe::streams::Stream;
+use crate::failures::Failure;
+use crate::profile::{Profile, select_profile};
+use crate::utils::validation::validate_input_file;
+use crate::utils::media::MediaDetector;
+use std::path::Path;
+
+const HEADERS: [&str; 6] = ["#", "type", "codec", "profile", "language", "disposition"];
+
+fn or_dash(value: Option<&String>) -> String {
+    return match value {
+        Some(value) => value.to_string(),
+        None => "-".to_string(),
+    };
+}
+
+fn stream_row(stream: &Stream) -> [String; 6] {
+    let flags = stream.get_disposition().flags();
+    return [
+        stream.get_index().to_string(),
+        stream.get_type_name().to_string(),
+        or_dash(stream.get_name()),
+        or_dash(stream.get_profile()),
+        or_dash(stream.get_language()),
+        if flags.is_empty() { "-".to_string() } else { flags.join(",") },
+    ];
+}
+
+fn print_table(rows: &[[String; 6]]) {
+    let mut widths = HEADERS.map(str::len);
+    for row in rows {
+        for (column, value) in row.iter().enumerate() {
+            widths[column] = widths[column].max(value.chars().count());
+        }
+    }
+    let print_row = |row: &[String]| {
+        let cells: Vec<String> = row.iter().enumerate()
+        .map(|(column, value)| format!("{value:<width$}", width = widths[column]))
+        .collect();
+        println!("{}", cells.join("  ").trim_end());
+    };
+    print_row(&HEADERS.map(str::to_string));
+    for row in rows {
+        print_row(row);
+    }
+}
+
+fn print_probe(probe: &Probe) {
+    if let Some(format) = probe.format() {
+        println!("container : {}", format.get_format_name());
+    }
+    let rows: Vec<[String; 6]> = probe.streams().iter().map(stream_row).collect();
+    print_table(&rows);
+}
+
+fn print_verdict(failures: &[Failure]) {
+    if failures.is_empty() {
+        println!("verdict : compatible");
+    } else {
+        println!("verdict : needs transcoding");
+        for failure in failures {
+            println!("  - {}", failure.get_message());
+        }
+    }
+}
+
+fn explain(media_location: &Path, profile: &Profile, prober: &dyn Prober) -> Result<(), LadonError> {
+    println!("{}", media_location.display());
+    return match prober.probe(media_location) {
+        Ok(probe) => {
+            print_probe(&probe);
+            print_verdict(&probe.failures(media_location, profile));
+            Ok(())
+        },
+        Err(source) => {
+            println!("verdict : {source}");
+            Err(LadonError::Probe { path: media_location.to_path_buf(), source })
+        },
+    };
+}
+
+/// Prints every stream of a media and why it would be recorded by `search` with the same rules.
+pub fn probe_media(media_location: &Path, profile_location: Option<&str>, target_name: Option<&str>, detector: &MediaDetector, prober: &dyn Prober) -> Result<(), LadonError> {
+    validate_input_file(media_location)?;
+    if !detector.is_media(media_location) {
+        return Err(LadonError::NotMedia(media_location.to_path_buf()));
+    }
+    let profile = select_profile(profile_location, target_name)?;
+    return explain(media_location, &profile, prober);
+}
@@ -0,0 +1,654 @@
+use crate::utils::validation::validate_input_file;
+use crate::errors::LadonError;
+use crate::targets::target_profile;
+use crate::ffprobe::streams::SubtitleKind;
+use crate::languages::same_language;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    return values.iter().map(|value| value.to_string()).collect();
+}
+
+fn contains(values: &[String], value: &str) -> bool {
+    return values.iter().any(|allowed| allowed.eq_ignore_ascii_case(value));
+}
+
+/// Whether a value is under its ceiling, a missing value or ceiling is never over.
+fn is_within<T: PartialOrd>(value: Option<T>, max: Option<T>) -> bool {
+    return match (value, max) {
+        (Some(value), Some(max)) => value <= max,
+        _ => true,
+    };
+}
+
+pub mod levels {
+
+    /// ffprobe reports H.264 levels multiplied by 10 (41 is level 4.1) and HEVC levels multiplied by 30 (153 is level 5.1).
+    fn level_scale(codec_name: &str) -> Option<f64> {
+        return match codec_name.to_ascii_lowercase().as_str() {
+            "h264" => Some(10.0),
+            "hevc" => Some(30.0),
+            _ => None,
+        };
+    }
+
+    /// Level as ffprobe reports it, from a level written like `4.1`.
+    pub fn from_written(codec_name: &str, written_level: &str) -> Option<i64> {
+        return match level_scale(codec_name) {
+            Some(scale) => written_level.parse::<f64>().ok().map(|level| (level * scale).round() as i64),
+            None => written_level.parse().ok(),
+        };
+    }
+
+    /// Level as it is usually written, `4.1` for the H.264 level reported as 41.
+    pub fn written(codec_name: &str, level: i64) -> String {
+        return match level_scale(codec_name) {
+            Some(scale) => format!("{:.1}", level as f64 / scale),
+            None => level.to_string(),
+        };
+    }
+
+}
+
+/// Maximum level, either as ffprobe reports it (`max_level = 41`) or as it is usually written (`max_level = "4.1"`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaxLevel {
+    Reported(i64),
+    Written(String),
+}
+
+/// Restrictions applied to the streams of one video codec, an empty or missing rule allows anything.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CodecConstraints {
+    profiles: Vec<String>,
+    max_level: Option<MaxLevel>,
+    max_bit_depth: Option<u32>,
+    pix_fmts: Vec<String>,
+}
+
+impl CodecConstraints {
+
+    pub fn profiles(&self) -> &Vec<String> {
+        return &self.profiles;
+    }
+
+    /// Converts a written maximum level to the level ffprobe reports, when the profile is parsed.
+    fn resolve_max_level(&mut self, codec_name: &str) -> Result<(), String> {
+        if let Some(MaxLevel::Written(max_level)) = &self.max_level {
+            let reported_max_level = levels::from_written(codec_name, max_level)
+            .ok_or_else(|| format!("invalid {codec_name} max_level {max_level:?}"))?;
+            self.max_level = Some(MaxLevel::Reported(reported_max_level));
+        }
+        return Ok(());
+    }
+
+    /// Maximum level as ffprobe reports it for this codec
+    pub fn max_level(&self, codec_name: &str) -> Option<i64> {
+        return match &self.max_level {
+            Some(MaxLevel::Reported(max_level)) => Some(*max_level),
+            Some(MaxLevel::Written(max_level)) => levels::from_written(codec_name, max_level),
+            None => None,
+        };
+    }
+
+    pub fn max_bit_depth(&self) -> Option<u32> {
+        return self.max_bit_depth;
+    }
+
+    pub fn pix_fmts(&self) -> &Vec<String> {
+        return &self.pix_fmts;
+    }
+
+    pub fn allows_profile(&self, profile: Option<&String>) -> bool {
+        return self.profiles.is_empty() || match profile {
+            Some(profile) => contains(&self.profiles, profile),
+            None => false,
+        };
+    }
+
+    pub fn allows_level(&self, codec_name: &str, level: Option<i64>) -> bool {
+        return match (self.max_level(codec_name), level) {
+            (Some(max_level), Some(level)) => level <= max_level,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+    }
+
+    pub fn allows_bit_depth(&self, bit_depth: Option<u32>) -> bool {
+        return match (self.max_bit_depth, bit_depth) {
+            (Some(max_bit_depth), Some(bit_depth)) => bit_depth <= max_bit_depth,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+    }
+
+    pub fn allows_pix_fmt(&self, pix_fmt: Option<&String>) -> bool {
+        return self.pix_fmts.is_empty() || match pix_fmt {
+            Some(pix_fmt) => contains(&self.pix_fmts, pix_fmt),
+            None => false,
+        };
+    }
+
+}
+
+/// HDR streams are allowed unless the profile marks their format as unsupported :
+/// HDR10 (PQ transfer), HLG, every Dolby Vision profile or some of them.
+/// Interlaced and variable frame rate streams are allowed unless the profile denies them.
+/// Streams larger or faster than the optional ceilings have to be downscaled.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoRules {
+    codecs: Vec<String>,
+    constraints: HashMap<String, CodecConstraints>,
+    allow_hdr10: bool,
+    allow_hlg: bool,
+    allow_dolby_vision: bool,
+    denied_dolby_vision_profiles: Vec<u8>,
+    allow_interlaced: bool,
+    allow_variable_frame_rate: bool,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    max_frame_rate: Option<f64>,
+}
+
+impl Default for VideoRules {
+    fn default() -> VideoRules {
+        VideoRules {
+            codecs: to_strings(&["h264"]),
+            constraints: HashMap::new(),
+            allow_hdr10: true,
+            allow_hlg: true,
+            allow_dolby_vision: true,
+            denied_dolby_vision_profiles: Vec::new(),
+            allow_interlaced: true,
+            allow_variable_frame_rate: true,
+            max_width: None,
+            max_height: None,
+            max_frame_rate: None,
+        }
+    }
+}
+
+impl VideoRules {
+
+    pub fn codecs(&self) -> &Vec<String> {
+        return &self.codecs;
+    }
+
+    pub fn allows_codec(&self, codec_name: &str) -> bool {
+        return contains(&self.codecs, codec_name);
+    }
+
+    pub fn constraints_for(&self, codec_name: &str) -> Option<&CodecConstraints> {
+        return self.constraints.get(&codec_name.to_ascii_lowercase());
+    }
+
+    fn resolve_max_levels(&mut self) -> Result<(), String> {
+        for (codec_name, constraints) in self.constraints.iter_mut() {
+            constraints.resolve_max_level(codec_name)?;
+        }
+        return Ok(());
+    }
+
+    pub fn allows_hdr10(&self) -> bool {
+        return self.allow_hdr10;
+    }
+
+    pub fn allows_hlg(&self) -> bool {
+        return self.allow_hlg;
+    }
+
+    pub fn allows_interlaced(&self) -> bool {
+        return self.allow_interlaced;
+    }
+
+    pub fn allows_variable_frame_rate(&self) -> bool {
+        return self.allow_variable_frame_rate;
+    }
+
+    pub fn max_width(&self) -> Option<u32> {
+        return self.max_width;
+    }
+
+    pub fn max_height(&self) -> Option<u32> {
+        return self.max_height;
+    }
+
+    pub fn max_frame_rate(&self) -> Option<f64> {
+        return self.max_frame_rate;
+    }
+
+    /// An unknown dimension is allowed, ffprobe always reports them for decodable streams.
+    pub fn allows_resolution(&self, width: Option<u32>, height: Option<u32>) -> bool {
+        return is_within(width, self.max_width) && is_within(height, self.max_height);
+    }
+
+    /// Frame rates are compared as written with two decimals, 60000/1001 fps is 59.94 fps.
+    pub fn allows_frame_rate(&self, frame_rate: Option<f64>) -> bool {
+        return is_within(frame_rate.map(|frame_rate| (frame_rate * 100.0).round() / 100.0), self.max_frame_rate);
+    }
+
+    pub fn allows_dolby_vision_profile(&self, dolby_vision_profile: u8) -> bool {
+        return self.allow_dolby_vision && !self.denied_dolby_vision_profiles.contains(&dolby_vision_profile);
+    }
+
+}
+
+/// Restrictions applied to the streams of one audio codec, an empty or missing rule allows anything.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConstraints {
+    profiles: Vec<String>,
+    max_channels: Option<u32>,
+    channel_layouts: Vec<String>,
+    sample_rates: Vec<u32>,
+    min_bit_rate: Option<u64>,
+    max_bit_rate: Option<u64>,
+}
+
+impl AudioConstraints {
+
+    pub fn profiles(&self) -> &Vec<String> {
+        return &self.profiles;
+    }
+
+    pub fn max_channels(&self) -> Option<u32> {
+        return self.max_channels;
+    }
+
+    pub fn channel_layouts(&self) -> &Vec<String> {
+        return &self.channel_layouts;
+    }
+
+    pub fn sample_rates(&self) -> &Vec<u32> {
+        return &self.sample_rates;
+    }
+
+    pub fn min_bit_rate(&self) -> Option<u64> {
+        return self.min_bit_rate;
+    }
+
+    pub fn max_bit_rate(&self) -> Option<u64> {
+        return self.max_bit_rate;
+    }
+
+    pub fn allows_profile(&self, profile: Option<&String>) -> bool {
+        return self.profiles.is_empty() || match profile {
+            Some(profile) => contains(&self.profiles, profile),
+            None => false,
+        };
+    }
+
+    pub fn allows_channels(&self, channels: Option<u32>) -> bool {
+        return match (self.max_channels, channels) {
+            (Some(max_channels), Some(channels)) => channels <= max_channels,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+    }
+
+    pub fn allows_channel_layout(&self, channel_layout: Option<&String>) -> bool {
+        return self.channel_layouts.is_empty() || match channel_layout {
+            Some(channel_layout) => contains(&self.channel_layouts, channel_layout),
+            None => false,
+        };
+    }
+
+    pub fn allows_sample_rate(&self, sample_rate: Option<u32>) -> bool {
+        return self.sample_rates.is_empty() || match sample_rate {
+            Some(sample_rate) => self.sample_rates.contains(&sample_rate),
+            None => false,
+        };
+    }
+
+    /// Bit rate is often missing from MKV audio streams, an unknown bit rate is always allowed.
+    pub fn allows_bit_rate(&self, bit_rate: Option<u64>) -> bool {
+        return match bit_rate {
+            Some(bit_rate) => self.min_bit_rate.is_none_or(|min| bit_rate >= min) && self.max_bit_rate.is_none_or(|max| bit_rate <= max),
+            None => true,
+        };
+    }
+
+}
+
+/// `languages` asks for at least one audio stream in one of them, and for the default audio stream
+/// to be in one of them with `require_default_language`. An empty list asks for nothing.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioRules {
+    codecs: Vec<String>,
+    constraints: HashMap<String, AudioConstraints>,
+    languages: Vec<String>,
+    require_default_language: bool,
+}
+
+impl Default for AudioRules {
+    fn default() -> AudioRules {
+        AudioRules {
+            codecs: to_strings(&["aac"]),
+            constraints: HashMap::new(),
+            languages: Vec::new(),
+            require_default_language: false,
+        }
+    }
+}
+
+impl AudioRules {
+
+    pub fn codecs(&self) -> &Vec<String> {
+        return &self.codecs;
+    }
+
+    pub fn allows_codec(&self, codec_name: &str) -> bool {
+        return contains(&self.codecs, codec_name);
+    }
+
+    pub fn constraints_for(&self, codec_name: &str) -> Option<&AudioConstraints> {
+        return self.constraints.get(&codec_name.to_ascii_lowercase());
+    }
+
+    pub fn languages(&self) -> &Vec<String> {
+        return &self.languages;
+    }
+
+    pub fn requires_language(&self) -> bool {
+        return !self.languages.is_empty();
+    }
+
+    pub fn allows_language(&self, language: &str) -> bool {
+        return self.languages.iter().any(|allowed_language| same_language(allowed_language, language));
+    }
+
+    pub fn requires_default_language(&self) -> bool {
+        return self.require_default_language && self.requires_language();
+    }
+
+}
+
+/// Languages match whatever way they are written, `fr`, `fre`, `French` and `fr-FR` are all `fra`.
+/// An empty `languages` list allows every language.
+/// Text subtitles are rendered by players, bitmap subtitles (PGS, VobSub, DVB) have to be burnt in by the server.
+/// Subtitles of an unknown kind, closed captions for example, are not checked.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubtitleRules {
+    languages: Vec<String>,
+    allow_text: bool,
+    allow_bitmap: bool,
+}
+
+impl Default for SubtitleRules {
+    fn default() -> SubtitleRules {
+        SubtitleRules {
+            languages: to_strings(&["fra", "eng", "und"]),
+            allow_text: true,
+            allow_bitmap: false,
+        }
+    }
+}
+
+impl SubtitleRules {
+
+    pub fn languages(&self) -> &Vec<String> {
+        return &self.languages;
+    }
+
+    pub fn allows_language(&self, language: &str) -> bool {
+        return self.languages.is_empty() || self.languages.iter().any(|allowed_language| same_language(allowed_language, language));
+    }
+
+    pub fn allows_kind(&self, kind: SubtitleKind) -> bool {
+        return match kind {
+            SubtitleKind::Text => self.allow_text,
+            SubtitleKind::Bitmap => self.allow_bitmap,
+            SubtitleKind::Unknown => true,
+        };
+    }
+
+}
+
+/// Containers are named after ffprobe demuxers, with the usual extensions as aliases (mp4, mov, mkv, webm, avi, ts...).
+/// An empty `formats` list allows every container that is not denied.
+/// Faststart and matching extensions are only checked when the profile asks for them.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContainerRules {
+    formats: Vec<String>,
+    denied_formats: Vec<String>,
+    require_faststart: bool,
+    require_matching_extension: bool,
+}
+
+impl Default for ContainerRules {
+    fn default() -> ContainerRules {
+        ContainerRules {
+            formats: to_strings(&["mp4", "mkv"]),
+            denied_formats: Vec::new(),
+            require_faststart: false,
+            require_matching_extension: false,
+        }
+    }
+}
+
+impl ContainerRules {
+
+    pub fn formats(&self) -> &Vec<String> {
+        return &self.formats;
+    }
+
+    pub fn denied_formats(&self) -> &Vec<String> {
+        return &self.denied_formats;
+    }
+
+    pub fn allows_format(&self, format: &str) -> bool {
+        return self.formats.is_empty() || contains(&self.formats, format);
+    }
+
+    pub fn denies_format(&self, format: &str) -> bool {
+        return contains(&self.denied_formats, format);
+    }
+
+    /// MP4 medias must have their index (`moov` atom) before their data to be streamed
+    pub fn requires_faststart(&self) -> bool {
+        return self.require_faststart;
+    }
+
+    /// Medias with an extension of another container, an MP4 named `.mkv` for example, must be renamed or remuxed
+    pub fn requires_matching_extension(&self) -> bool {
+        return self.require_matching_extension;
+    }
+
+}
+
+/// Rules of the streams that are not played : cover art, timed thumbnails, attachments and data.
+/// They are allowed with any codec unless the profile says otherwise.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtraStreamRules {
+    allowed: bool,
+    codecs: Vec<String>,
+}
+
+impl Default for ExtraStreamRules {
+    fn default() -> ExtraStreamRules {
+        ExtraStreamRules {
+            allowed: true,
+            codecs: Vec::new(),
+        }
+    }
+}
+
+impl ExtraStreamRules {
+
+    pub fn is_allowed(&self) -> bool {
+        return self.allowed;
+    }
+
+    pub fn codecs(&self) -> &Vec<String> {
+        return &self.codecs;
+    }
+
+    pub fn allows_codec(&self, codec_name: Option<&String>) -> bool {
+        return self.codecs.is_empty() || match codec_name {
+            Some(codec_name) => contains(&self.codecs, codec_name),
+            None => false,
+        };
+    }
+
+}
+
+/// Thresholds of the space saving search : medias whose video uses more bits per pixel, or more bits per second,
+/// than allowed are wastefully large. Their savings are estimated for a video re-encoded at the target bit rate,
+/// or at the target bits per pixel for their resolution and frame rate when no target bit rate is set.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpaceSavingRules {
+    max_bits_per_pixel: Option<f64>,
+    max_bit_rate: Option<u64>,
+    target_bits_per_pixel: f64,
+    target_bit_rate: Option<u64>,
+}
+
+impl Default for SpaceSavingRules {
+    fn default() -> SpaceSavingRules {
+        SpaceSavingRules {
+            max_bits_per_pixel: Some(0.2),
+            max_bit_rate: None,
+            target_bits_per_pixel: 0.1,
+            target_bit_rate: None,
+        }
+    }
+}
+
+impl SpaceSavingRules {
+
+    pub fn max_bits_per_pixel(&self) -> Option<f64> {
+        return self.max_bits_per_pixel;
+    }
+
+    pub fn max_bit_rate(&self) -> Option<u64> {
+        return self.max_bit_rate;
+    }
+
+    pub fn target_bits_per_pixel(&self) -> f64 {
+        return self.target_bits_per_pixel;
+    }
+
+    pub fn target_bit_rate(&self) -> Option<u64> {
+        return self.target_bit_rate;
+    }
+
+    pub fn allows_bits_per_pixel(&self, bits_per_pixel: Option<f64>) -> bool {
+        return is_within(bits_per_pixel, self.max_bits_per_pixel);
+    }
+
+    pub fn allows_bit_rate(&self, bit_rate: Option<u64>) -> bool {
+        return is_within(bit_rate, self.max_bit_rate);
+    }
+
+}
+
+/// Compatibility rules a media must follow to be played without transcoding.
+/// Every section falls back to the built-in rules when missing from the profile file.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    video: VideoRules,
+    audio: AudioRules,
+    subtitle: SubtitleRules,
+    container: ContainerRules,
+    cover_art: ExtraStreamRules,
+    timed_thumbnails: ExtraStreamRules,
+    attachment: ExtraStreamRules,
+    data: ExtraStreamRules,
+    space_saving: SpaceSavingRules,
+}
+
+impl Profile {
+
+    pub fn video(&self) -> &VideoRules {
+        return &self.video;
+    }
+
+    pub fn audio(&self) -> &AudioRules {
+        return &self.audio;
+    }
+
+    pub fn subtitle(&self) -> &SubtitleRules {
+        return &self.subtitle;
+    }
+
+    pub fn container(&self) -> &ContainerRules {
+        return &self.container;
+    }
+
+    pub fn cover_art(&self) -> &ExtraStreamRules {
+        return &self.cover_art;
+    }
+
+    pub fn timed_thumbnails(&self) -> &ExtraStreamRules {
+        return &self.timed_thumbnails;
+    }
+
+    pub fn attachment(&self) -> &ExtraStreamRules {
+        return &self.attachment;
+    }
+
+    pub fn data(&self) -> &ExtraStreamRules {
+        return &self.data;
+    }
+
+    pub fn space_saving(&self) -> &SpaceSavingRules {
+        return &self.space_saving;
+    }
+
+}
+
+/// Codec names are matched lowercased, so `[video.constraints.H264]` constrains h264 streams.
+fn lowercase_codec_names<T>(constraints: &mut HashMap<String, T>) -> Result<(), String> {
+    for (codec_name, codec_constraints) in std::mem::take(constraints) {
+        if constraints.insert(codec_name.to_ascii_lowercase(), codec_constraints).is_some() {
+            return Err(format!("constraints of codec {codec_name} given twice"));
+        }
+    }
+    return Ok(());
+}
+
+/// Constrained codec names and written levels are checked here, so that an invalid one stops the run
+/// before any media is searched.
+pub fn parse_profile(profile_name: &str, content: &str) -> Result<Profile, LadonError> {
+    let invalid = |reason: String| LadonError::Profile { name: profile_name.to_string(), reason };
+    let mut profile: Profile = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+    lowercase_codec_names(&mut profile.video.constraints).map_err(invalid)?;
+    lowercase_codec_names(&mut profile.audio.constraints).map_err(invalid)?;
+    profile.video.resolve_max_levels().map_err(invalid)?;
+    return Ok(profile);
+}
+
+fn read_profile(profile_location: &str) -> Result<Profile, LadonError> {
+    validate_input_file(Path::new(profile_location))?;
+    return match read_to_string(profile_location) {
+        Ok(content) => parse_profile(profile_location, &content),
+        Err(e) => Err(LadonError::io("reading profile", profile_location, e)),
+    };
+}
+
+pub fn load_profile(profile_location: Option<&str>) -> Result<Profile, LadonError> {
+    return match profile_location {
+        Some(location) => read_profile(location),
+        None => Ok(Profile::default()),
+    };
+}
+
+/// Rules in use : a built-in target, a profile file or the built-in profile.
+pub fn select_profile(profile_location: Option<&str>, target_name: Option<&str>) -> Result<Profile, LadonError> {
+    return match target_name {
+        Some(target_name) => target_profile(target_name),
+        None => load_profile(profile_location),
+    };
+}
@@ -0,0 +1,139 @@
+use crate::errors::LadonError;
+use crate::failures::Failure;
+use crate::verify::Damage;
+use crate::savings::Saving;
+use crate::utils::list::escaped;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+struct MediaReport<'a> {
+    path: String,
+    failures: &'a Vec<Failure>,
+}
+
+/// Report written next to the output file : `to_transcode.txt` is explained by `to_transcode.jsonl`.
+/// The extension is appended when the output file already has the report extension.
+pub fn report_location(output_file_location: &Path, extension: &str) -> PathBuf {
+    return match output_file_location.extension() {
+        Some(output_extension) if output_extension.eq(extension) => {
+            let mut report_location = output_file_location.as_os_str().to_os_string();
+            report_location.push(format!(".{extension}"));
+            PathBuf::from(report_location)
+        },
+        _ => output_file_location.with_extension(extension),
+    };
+}
+
+fn report_writer(report_location: &Path) -> Result<BufWriter<File>, LadonError> {
+    return match File::create(report_location) {
+        Ok(report_file) => Ok(BufWriter::new(report_file)),
+        Err(e) => Err(LadonError::io("creating report", report_location, e)),
+    };
+}
+
+fn written(result: std::io::Result<()>, report_location: &Path) -> Result<(), LadonError> {
+    return result.map_err(|e| LadonError::io("writing report", report_location, e));
+}
+
+/// One JSON object per media, holding every failure of the media. Paths are escaped like in the media list.
+fn write_jsonl(report_location: &Path, medias: &[(&PathBuf, Vec<Failure>)]) -> Result<(), LadonError> {
+    let mut report_writer = report_writer(report_location)?;
+    for (media_location, failures) in medias {
+        let media_report = MediaReport { path: escaped(media_location), failures };
+        let line = report_line(&media_report, media_location);
+        written(writeln!(report_writer, "{line}"), report_location)?;
+    }
+    return written(report_writer.flush(), report_location);
+}
+
+#[derive(Serialize)]
+struct DamageReport<'a> {
+    path: String,
+    damages: &'a Vec<Damage>,
+}
+
+fn report_line<T: Serialize>(report: &T, media_location: &Path) -> String {
+    return match serde_json::to_string(report) {
+        Ok(line) => line,
+        Err(e) => panic!("Error serializing report of {media_location:?} : {e:?}")
+    };
+}
+
+fn csv_field(value: &str) -> String {
+    return format!("\"{}\"", value.replace('"', "\"\""));
+}
+
+/// One row per failure.
+fn write_csv(report_location: &Path, medias: &[(&PathBuf, Vec<Failure>)]) -> Result<(), LadonError> {
+    let mut report_writer = report_writer(report_location)?;
+    written(writeln!(report_writer, "path,stream_index,stream_type,rule,message"), report_location)?;
+    for (media_location, failures) in medias {
+        for failure in failures {
+            let row = [
+                csv_field(&escaped(media_location)),
+                failure.get_stream_index().map_or(String::new(), |index| index.to_string()),
+                failure.get_stream_type().map_or(String::new(), |stream_type| csv_field(stream_type)),
+                failure.get_rule().code().to_string(),
+                csv_field(failure.get_message()),
+            ].join(",");
+            written(writeln!(report_writer, "{row}"), report_location)?;
+        }
+    }
+    return written(report_writer.flush(), report_location);
+}
+
+pub fn write_reports(output_file_location: &Path, medias: &[(&PathBuf, Vec<Failure>)]) -> Result<(), LadonError> {
+    write_jsonl(&report_location(output_file_location, "jsonl"), medias)?;
+    return write_csv(&report_location(output_file_location, "csv"), medias);
+}
+
+#[derive(Serialize)]
+struct SavingReport<'a> {
+    path: String,
+    #[serde(flatten)]
+    saving: &'a Saving,
+}
+
+/// Wastefully large medias found by the space saving search, in the same order as the media list :
+/// one JSON object per media and one CSV row per media.
+pub fn write_saving_reports(output_file_location: &Path, medias: &[(&PathBuf, Saving)]) -> Result<(), LadonError> {
+    let jsonl_location = report_location(output_file_location, "jsonl");
+    let mut jsonl_writer = report_writer(&jsonl_location)?;
+    for (media_location, saving) in medias {
+        let line = report_line(&SavingReport { path: escaped(media_location), saving }, media_location);
+        written(writeln!(jsonl_writer, "{line}"), &jsonl_location)?;
+    }
+    written(jsonl_writer.flush(), &jsonl_location)?;
+    let csv_location = report_location(output_file_location, "csv");
+    let mut csv_writer = report_writer(&csv_location)?;
+    written(writeln!(csv_writer, "path,reasons,size,video_bit_rate,bits_per_pixel,target_bit_rate,estimated_size,estimated_savings"), &csv_location)?;
+    for (media_location, saving) in medias {
+        let reasons: Vec<&str> = saving.get_reasons().iter().map(|reason| reason.code()).collect();
+        let row = [
+            csv_field(&escaped(media_location)),
+            csv_field(&reasons.join(" ")),
+            saving.get_size().to_string(),
+            saving.get_video_bit_rate().to_string(),
+            saving.get_bits_per_pixel().map_or(String::new(), |bits_per_pixel| format!("{bits_per_pixel:.3}")),
+            saving.get_target_bit_rate().map_or(String::new(), |target_bit_rate| target_bit_rate.to_string()),
+            saving.get_estimated_size().to_string(),
+            saving.get_estimated_savings().to_string(),
+        ].join(",");
+        written(writeln!(csv_writer, "{row}"), &csv_location)?;
+    }
+    return written(csv_writer.flush(), &csv_location);
+}
+
+/// Damages found by `verify`, one JSON object per damaged media.
+pub fn write_damage_report(output_file_location: &Path, medias: &[(&PathBuf, Vec<Damage>)]) -> Result<(), LadonError> {
+    let report_location = report_location(output_file_location, "jsonl");
+    let mut report_writer = report_writer(&report_location)?;
+    for (media_location, damages) in medias {
+        let line = report_line(&DamageReport { path: escaped(media_location), damages }, media_location);
+        written(writeln!(report_writer, "{line}"), &report_location)?;
+    }
+    return written(report_writer.flush(), &report_location);
+}
@@ -0,0 +1,134 @@
+use crate::ffprobe::Probe;
+use crate::ffprobe::streams::{Stream, StreamClass};
+use crate::profile::SpaceSavingRules;
+use serde::Serialize;
+
+/// Space saving threshold a media went over, written as a snake case reason code in reports.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SavingReason {
+    BitsPerPixel,
+    BitRate,
+}
+
+impl SavingReason {
+
+    pub fn code(&self) -> &'static str {
+        return match self {
+            Self::BitsPerPixel => "bits_per_pixel",
+            Self::BitRate => "bit_rate",
+        };
+    }
+
+}
+
+/// Space a wastefully large media would take once its video is re-encoded at the target bit rate,
+/// audio and other streams being kept as they are.
+#[derive(Serialize)]
+pub struct Saving {
+    reasons: Vec<SavingReason>,
+    size: u64,
+    video_bit_rate: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bits_per_pixel: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_bit_rate: Option<u64>,
+    estimated_size: u64,
+    estimated_savings: u64,
+}
+
+impl Saving {
+
+    pub fn get_reasons(&self) -> &Vec<SavingReason> {
+        return &self.reasons;
+    }
+
+    pub fn get_size(&self) -> u64 {
+        return self.size;
+    }
+
+    pub fn get_video_bit_rate(&self) -> u64 {
+        return self.video_bit_rate;
+    }
+
+    pub fn get_bits_per_pixel(&self) -> Option<f64> {
+        return self.bits_per_pixel;
+    }
+
+    /// Video bit rate of the re-encoded media, unknown when it depends on an unknown resolution or frame rate
+    pub fn get_target_bit_rate(&self) -> Option<u64> {
+        return self.target_bit_rate;
+    }
+
+    pub fn get_estimated_size(&self) -> u64 {
+        return self.estimated_size;
+    }
+
+    pub fn get_estimated_savings(&self) -> u64 {
+        return self.estimated_savings;
+    }
+
+}
+
+/// Pixels shown each second by a video stream.
+fn pixel_rate(video: &Stream) -> Option<f64> {
+    let pixels = video.get_width()? as f64 * video.get_height()? as f64;
+    return Some(pixels * video.frame_rate()?).filter(|pixel_rate| *pixel_rate > 0.0);
+}
+
+/// Bit rate of the video stream, or what is left of the media bit rate once the other streams are taken out
+/// when the container does not tell it, like Matroska.
+fn video_bit_rate(probe: &Probe, video: &Stream) -> Option<u64> {
+    if let Some(bit_rate) = video.get_bit_rate() {
+        return Some(bit_rate);
+    }
+    let format = probe.format()?;
+    let media_bit_rate = match format.get_bit_rate() {
+        Some(bit_rate) => bit_rate,
+        None => (format.get_size()? as f64 * 8.0 / format.get_duration().filter(|duration| *duration > 0.0)?) as u64,
+    };
+    let other_bit_rates: u64 = probe.streams().iter()
+    .filter(|stream| stream.get_index() != video.get_index())
+    .filter_map(|stream| stream.get_bit_rate())
+    .sum();
+    return Some(media_bit_rate.saturating_sub(other_bit_rates)).filter(|bit_rate| *bit_rate > 0);
+}
+
+/// Savings of a media over the space saving thresholds, None for medias that are not wastefully large
+/// or whose size, duration or video bit rate is unknown.
+pub fn media_saving(probe: &Probe, rules: &SpaceSavingRules) -> Option<Saving> {
+    let format = probe.format()?;
+    let size = format.get_size()?;
+    let duration = format.get_duration()?;
+    let video = probe.streams().iter().find(|stream| StreamClass::Video.eq(&stream.class()))?;
+    let video_bit_rate = video_bit_rate(probe, video)?;
+    let pixel_rate = pixel_rate(video);
+    let bits_per_pixel = pixel_rate.map(|pixel_rate| video_bit_rate as f64 / pixel_rate);
+    let mut reasons = Vec::new();
+    if !rules.allows_bits_per_pixel(bits_per_pixel) {
+        reasons.push(SavingReason::BitsPerPixel);
+    }
+    if !rules.allows_bit_rate(Some(video_bit_rate)) {
+        reasons.push(SavingReason::BitRate);
+    }
+    if reasons.is_empty() {
+        return None;
+    }
+    let target_bit_rate = rules.target_bit_rate()
+    .or_else(|| pixel_rate.map(|pixel_rate| (pixel_rate * rules.target_bits_per_pixel()) as u64))
+    .map(|target_bit_rate| target_bit_rate.min(video_bit_rate));
+    let saved_bytes = match target_bit_rate {
+        Some(target_bit_rate) => ((video_bit_rate - target_bit_rate) as f64 * duration / 8.0) as u64,
+        None => 0,
+    };
+    let estimated_size = size.saturating_sub(saved_bytes);
+    return Some(Saving {
+        reasons,
+        size,
+        video_bit_rate,
+        bits_per_pixel,
+        target_bit_rate,
+        estimated_size,
+        estimated_savings: size - estimated_size,
+    });
+}
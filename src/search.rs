@@ -1,67 +1,209 @@
-use crate::ffprobe::ffprobe;
-use crate::ffprobe::streams::{Codec, Stream};
+use crate::errors::{ErrorLog, LadonError};
+use crate::ffprobe::probers::Prober;
+use crate::failures::Failure;
+use crate::report::{write_reports, write_saving_reports};
+use crate::savings::{Saving, media_saving};
 use crate::utils::validation::{validate_directory, validate_output_file};
 use crate::utils::path::{directory_entries, location_of, type_of};
-use crate::utils::media::{is_avi_file, is_non_avi_video_file};
-use std::borrow::Borrow;
-use std::fs::File;
-use std::rc::Rc;
-use std::cell::RefCell;
-use std::io::{Result, BufWriter, Write};
+use crate::utils::media::MediaDetector;
+use crate::utils::list::{ListFormat, write_list};
+use crate::profile::{Profile, select_profile};
+use crate::cache::CachedProber;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 
-fn is_invalid_media_stream(stream: Stream) -> bool {
-    return match stream.get_codec() {
-        Some(codec) => match codec {
-            Codec::Video => return !stream.is_valid_video_stream(),
-            Codec::Audio => return !stream.is_valid_audio_stream(),
-            Codec::Subtitle => return !stream.is_valid_subtitle_stream(),
-            _ => panic!("Invalid stream processing : codec type '{codec:?}'"),
-        },
-        None => panic!("Invalid stream processing : no codec type")
-    };
+/// Order of the medias in the output file.
+#[derive(Clone, clap::ValueEnum)]
+pub enum Order {
+    /// Sorted by path
+    Path,
+    /// As found while walking the media directory
+    Walk,
 }
 
-fn need_transcoding(media_location: &str) -> bool {
-    return match ffprobe(media_location) {
-        Ok(mut ffprobe) => ffprobe.any(is_invalid_media_stream),
-        Err(e) => panic!("ffprobe error {media_location}: {e:?}"),
+/// What the search looks for.
+#[derive(Clone, clap::ValueEnum)]
+pub enum Mode {
+    /// Medias that do not follow the compatibility rules of the profile
+    Compatibility,
+    /// Medias above the space saving thresholds of the profile, largest estimated savings first
+    SpaceSaving,
+}
+
+pub struct SearchOptions<'a> {
+    pub profile_location: Option<&'a str>,
+    pub target_name: Option<&'a str>,
+    pub jobs: usize,
+    pub mode: Mode,
+    pub order: Order,
+    /// Probe cache file, no cache is used when missing
+    pub cache_location: Option<String>,
+    /// Forget every cached probe and probe all medias again
+    pub rebuild_cache: bool,
+    /// Extensions of the files to check, whatever their case
+    pub extensions: Vec<String>,
+    /// Also check the files recognized as medias by their content
+    pub sniff: bool,
+    /// Format of the output list, read back by `export`
+    pub list_format: ListFormat,
+}
+
+struct Search<'a> {
+    profile: Profile,
+    prober: &'a dyn Prober,
+    jobs: usize,
+    mode: Mode,
+    order: Order,
+    detector: MediaDetector,
+    list_format: ListFormat,
+    errors: &'a ErrorLog,
+}
+
+fn media_failures(media_location: &Path, search: &Search) -> Result<Vec<Failure>, LadonError> {
+    return match search.prober.probe(media_location) {
+        Ok(probe) => Ok(probe.failures(media_location, &search.profile)),
+        Err(source) => Err(LadonError::Probe { path: media_location.to_path_buf(), source }),
     };
 }
 
-fn should_record_file(location: &str) -> bool {
-    return is_avi_file(location) || (is_non_avi_video_file(location) && need_transcoding(location));
+fn media_space_saving(media_location: &Path, search: &Search) -> Result<Option<Saving>, LadonError> {
+    return match search.prober.probe(media_location) {
+        Ok(probe) => Ok(media_saving(&probe, search.profile.space_saving())),
+        Err(source) => Err(LadonError::Probe { path: media_location.to_path_buf(), source }),
+    };
 }
 
-fn search_recursively(directory_location: &str, output_writer_ref: Rc<RefCell<BufWriter<File>>>) {
-    for entry in directory_entries(directory_location) {
+/// Medias found in a directory and its sub-directories, in walk order.
+pub fn collect_medias(directory_location: &Path, detector: &MediaDetector, errors: &ErrorLog, medias: &mut Vec<PathBuf>) -> Result<(), LadonError> {
+    let entries = match errors.skip(directory_entries(directory_location))? {
+        Some(entries) => entries,
+        None => return Ok(()),
+    };
+    for entry in entries {
+        let entry = match errors.skip(entry)? {
+            Some(entry) => entry,
+            None => continue,
+        };
         let entry_location = location_of(&entry);
-        let entry_type = type_of(&entry, &entry_location);
+        let entry_type = match errors.skip(type_of(&entry, &entry_location))? {
+            Some(entry_type) => entry_type,
+            None => continue,
+        };
         if entry_type.is_dir() {
-            search_recursively(&entry_location, output_writer_ref.clone())
-        } else if should_record_file(&entry_location) {
-            let mut output_writer = output_writer_ref.borrow_mut();
-            writeln!(output_writer, "{entry_location}");
+            collect_medias(&entry_location, detector, errors, medias)?;
+        } else if detector.is_media(&entry_location) {
+            medias.push(entry_location);
         }
     }
+    return Ok(());
 }
 
-fn output_file(output_file_location: &str) -> File {
-    return match File::create(output_file_location) {
-        Ok(output_file) => output_file,
-        Err(e) => panic!("Error while creating output file {output_file_location:?} : {e:?}")
+/// Workers take the next media to check from a shared index, results keep the walk order.
+/// Medias that could not be checked are left out, the first error stops every worker unless the run keeps going.
+pub fn check_medias<T, F>(medias: &[PathBuf], jobs: usize, errors: &ErrorLog, check: F) -> Result<Vec<(usize, T)>, LadonError>
+where T: Send, F: Fn(&Path) -> Result<T, LadonError> + Sync {
+    let next_media = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let stop_error: Mutex<Option<LadonError>> = Mutex::new(None);
+    let mut checks: Vec<(usize, T)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.max(1)).map(|_| scope.spawn(|| {
+            let mut worker_checks = Vec::new();
+            while !stopped.load(Ordering::Relaxed) {
+                let index = next_media.fetch_add(1, Ordering::Relaxed);
+                let media_location = match medias.get(index) {
+                    Some(media_location) => media_location,
+                    None => break,
+                };
+                match errors.skip(check(media_location)) {
+                    Ok(Some(media_check)) => worker_checks.push((index, media_check)),
+                    Ok(None) => (),
+                    Err(e) => {
+                        stopped.store(true, Ordering::Relaxed);
+                        stop_error.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(e);
+                    },
+                }
+            }
+            return worker_checks;
+        })).collect();
+        return workers.into_iter()
+        .flat_map(|worker| match worker.join() {
+            Ok(worker_checks) => worker_checks,
+            Err(e) => panic!("Search worker failed : {e:?}"),
+        })
+        .collect();
+    });
+    if let Some(e) = stop_error.into_inner().unwrap_or_else(|e| e.into_inner()) {
+        return Err(e);
     }
+    checks.sort_by_key(|(index, _)| *index);
+    return Ok(checks);
+}
+
+/// Wastefully large medias ranked by estimated savings, medias of equal savings keep the search order.
+fn search_medias_to_save_space(medias: &[PathBuf], output_file_location: &Path, search: &Search) -> Result<(), LadonError> {
+    let checks = check_medias(medias, search.jobs, search.errors, |media_location| media_space_saving(media_location, search))?;
+    let mut medias_to_shrink: Vec<(&PathBuf, Saving)> = checks.into_iter()
+    .filter_map(|(index, saving)| saving.map(|saving| (&medias[index], saving)))
+    .collect();
+    medias_to_shrink.sort_by_key(|(_, saving)| std::cmp::Reverse(saving.get_estimated_savings()));
+    let estimated_savings: u64 = medias_to_shrink.iter().map(|(_, saving)| saving.get_estimated_savings()).sum();
+    println!("{} wastefully large medias, about {:.1} GB could be saved", medias_to_shrink.len(), estimated_savings as f64 / 1_000_000_000.0);
+    let media_locations: Vec<&Path> = medias_to_shrink.iter().map(|(media_location, _)| media_location.as_path()).collect();
+    write_list(output_file_location, &media_locations, search.list_format)?;
+    return write_saving_reports(output_file_location, &medias_to_shrink);
 }
 
-fn start_searching(directory_location: &str, output_file_location: &str) {
-    let output_file = output_file(output_file_location);
-    let mut output_writer = BufWriter::new(output_file);
-    let output_writer_ref = Rc::new(RefCell::new(output_writer));
-    search_recursively(directory_location, output_writer_ref);
+fn start_searching(directory_location: &Path, output_file_location: &Path, search: &Search) -> Result<(), LadonError> {
+    let mut medias = Vec::new();
+    collect_medias(directory_location, &search.detector, search.errors, &mut medias)?;
+    if let Order::Path = search.order {
+        medias.sort_by(|media, other_media| media.as_os_str().cmp(other_media.as_os_str()));
+    }
+    if let Mode::SpaceSaving = search.mode {
+        return search_medias_to_save_space(&medias, output_file_location, search);
+    }
+    let checks = check_medias(&medias, search.jobs, search.errors, |media_location| media_failures(media_location, search))?;
+    let medias_to_transcode: Vec<(&PathBuf, Vec<Failure>)> = checks.into_iter()
+    .filter(|(_, failures)| !failures.is_empty())
+    .map(|(index, failures)| (&medias[index], failures))
+    .collect();
+    let media_locations: Vec<&Path> = medias_to_transcode.iter().map(|(media_location, _)| media_location.as_path()).collect();
+    write_list(output_file_location, &media_locations, search.list_format)?;
+    return write_reports(output_file_location, &medias_to_transcode);
 }
 
-pub fn search_medias_to_transcode(directory_location: &str, output_file_location: &str) {
+pub fn search_medias_to_transcode(directory_location: &Path, output_file_location: &Path, options: SearchOptions, prober: &dyn Prober, errors: &ErrorLog) -> Result<(), LadonError> {
     println!("search directory: {directory_location:?}, write output: {output_file_location:?}");
-    validate_directory(directory_location);
-    validate_output_file(output_file_location);
-    start_searching(directory_location, output_file_location);
-}
\ No newline at end of file
+    validate_directory(directory_location)?;
+    validate_output_file(output_file_location)?;
+    let profile = select_profile(options.profile_location, options.target_name)?;
+    return match &options.cache_location {
+        Some(cache_location) => {
+            let cached_prober = CachedProber::new(prober, cache_location, options.rebuild_cache);
+            let searched = start_searching(directory_location, output_file_location, &Search {
+                profile,
+                prober: &cached_prober,
+                jobs: options.jobs,
+                mode: options.mode,
+                order: options.order,
+                detector: MediaDetector::new(options.extensions, options.sniff),
+                list_format: options.list_format,
+                errors,
+            });
+            cached_prober.save();
+            searched
+        },
+        None => start_searching(directory_location, output_file_location, &Search {
+            profile,
+            prober,
+            jobs: options.jobs,
+            mode: options.mode,
+            order: options.order,
+            detector: MediaDetector::new(options.extensions, options.sniff),
+            list_format: options.list_format,
+            errors,
+        }),
+    };
+}
@@ -0,0 +1,219 @@
+use crate::errors::{ErrorLog, LadonError};
+use crate::cache::CachedProber;
+use crate::container::container_names;
+use crate::ffprobe::Probe;
+use crate::ffprobe::probers::Prober;
+use crate::ffprobe::streams::{Stream, StreamClass};
+use crate::languages::normalize;
+use crate::profile::{Profile, select_profile};
+use crate::search::{collect_medias, check_medias};
+use crate::utils::validation::validate_directory;
+use crate::utils::media::MediaDetector;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::metadata;
+use std::path::{Path, PathBuf};
+
+const GIGABYTE: f64 = 1_000_000_000.0;
+
+/// Width of the bar of a category holding every media.
+const BAR_WIDTH: usize = 30;
+
+const UNKNOWN: &str = "unknown";
+
+const NO_SUBTITLES: &str = "none";
+
+pub struct StatsOptions<'a> {
+    pub profile_location: Option<&'a str>,
+    pub target_name: Option<&'a str>,
+    pub jobs: usize,
+    /// Probe cache file shared with `search`, no cache is used when missing
+    pub cache_location: Option<String>,
+    /// Forget every cached probe and probe all medias again
+    pub rebuild_cache: bool,
+    pub extensions: Vec<String>,
+    pub sniff: bool,
+}
+
+/// Categories of one media in each histogram, a media with several streams of a kind counts once per category.
+struct MediaStats {
+    size: u64,
+    video_codecs: BTreeSet<String>,
+    audio_codecs: BTreeSet<String>,
+    container: String,
+    resolution: String,
+    subtitle_languages: BTreeSet<String>,
+    dynamic_range: String,
+    failing: bool,
+}
+
+/// Number of medias and their total size for each category.
+#[derive(Default)]
+struct Histogram {
+    categories: HashMap<String, (usize, u64)>,
+}
+
+impl Histogram {
+
+    fn add(&mut self, category: &str, size: u64) {
+        let (count, total_size) = self.categories.entry(category.to_string()).or_default();
+        *count += 1;
+        *total_size += size;
+    }
+
+    /// Categories holding the most medias first.
+    fn sorted(&self) -> Vec<(&String, &(usize, u64))> {
+        let mut categories: Vec<(&String, &(usize, u64))> = self.categories.iter().collect();
+        categories.sort_by(|(name, (count, _)), (other_name, (other_count, _))| other_count.cmp(count).then(name.cmp(other_name)));
+        return categories;
+    }
+
+}
+
+fn gigabytes(size: u64) -> String {
+    return format!("{:.1} GB", size as f64 / GIGABYTE);
+}
+
+/// Resolution class from the width or the height, a 1920x800 scope movie is 1080p like a 1440x1080 one.
+fn resolution_name(video: Option<&Stream>) -> String {
+    let (width, height) = match video.and_then(|video| Some((video.get_width()?, video.get_height()?))) {
+        Some(dimensions) => dimensions,
+        None => return UNKNOWN.to_string(),
+    };
+    let name = match (width, height) {
+        (width, height) if width >= 3200 || height >= 1800 => "2160p",
+        (width, height) if width >= 2240 || height >= 1260 => "1440p",
+        (width, height) if width >= 1600 || height >= 900 => "1080p",
+        (width, height) if width >= 1120 || height >= 630 => "720p",
+        _ => "SD",
+    };
+    return name.to_string();
+}
+
+/// Dolby Vision first, a Dolby Vision stream may also carry an HDR10 or HLG base layer.
+fn dynamic_range_name(video: Option<&Stream>) -> String {
+    return match video.and_then(|video| video.hdr_formats().into_iter().next()) {
+        Some(hdr_format) => hdr_format,
+        None if video.is_some() => "SDR".to_string(),
+        None => UNKNOWN.to_string(),
+    };
+}
+
+fn codecs(probe: &Probe, class: StreamClass) -> BTreeSet<String> {
+    return probe.streams().iter()
+    .filter(|stream| class.eq(&stream.class()))
+    .map(|stream| stream.get_name().map_or(UNKNOWN.to_string(), |name| name.to_lowercase()))
+    .collect();
+}
+
+/// Languages written as ISO 639-2/T codes when known, subtitles without language are undetermined
+/// and medias without subtitles are counted apart.
+fn subtitle_languages(probe: &Probe) -> BTreeSet<String> {
+    let mut languages: BTreeSet<String> = probe.streams().iter()
+    .filter(|stream| stream.is_subtitle())
+    .map(|stream| match stream.get_language() {
+        Some(language) => normalize(language).map_or(language.trim().to_lowercase(), str::to_string),
+        None => "und".to_string(),
+    })
+    .collect();
+    if languages.is_empty() {
+        languages.insert(NO_SUBTITLES.to_string());
+    }
+    return languages;
+}
+
+/// Size reported by ffprobe, or the file size when ffprobe does not tell it.
+fn media_size(media_location: &Path, probe: &Probe) -> Result<u64, LadonError> {
+    if let Some(size) = probe.format().and_then(|format| format.get_size()) {
+        return Ok(size);
+    }
+    return match metadata(media_location) {
+        Ok(media_metadata) => Ok(media_metadata.len()),
+        Err(e) => Err(LadonError::io("reading size of", media_location, e)),
+    };
+}
+
+fn media_stats(media_location: &Path, prober: &dyn Prober, profile: &Profile) -> Result<MediaStats, LadonError> {
+    let probe = match prober.probe(media_location) {
+        Ok(probe) => probe,
+        Err(source) => return Err(LadonError::Probe { path: media_location.to_path_buf(), source }),
+    };
+    let video = probe.streams().iter().find(|stream| StreamClass::Video.eq(&stream.class()));
+    return Ok(MediaStats {
+        size: media_size(media_location, &probe)?,
+        video_codecs: codecs(&probe, StreamClass::Video),
+        audio_codecs: codecs(&probe, StreamClass::Audio),
+        container: probe.format().and_then(|format| container_names(media_location, format, probe.streams()).into_iter().next()).unwrap_or(UNKNOWN.to_string()),
+        resolution: resolution_name(video),
+        subtitle_languages: subtitle_languages(&probe),
+        dynamic_range: dynamic_range_name(video),
+        failing: !probe.failures(media_location, profile).is_empty(),
+    });
+}
+
+fn print_histogram(title: &str, histogram: &Histogram, media_count: usize) {
+    println!("\n{title}");
+    let categories = histogram.sorted();
+    let name_width = categories.iter().map(|(name, _)| name.chars().count()).max().unwrap_or_default();
+    for (name, (count, size)) in categories {
+        let share = *count as f64 / media_count as f64;
+        let bar = "#".repeat((share * BAR_WIDTH as f64).round() as usize);
+        println!("  {name:<name_width$}  {count:>6}  {:>5.1}%  {:>10}  {bar}", share * 100.0, gigabytes(*size));
+    }
+}
+
+fn print_stats(medias: &[MediaStats]) {
+    let total_size: u64 = medias.iter().map(|media| media.size).sum();
+    println!("{} medias, {}", medias.len(), gigabytes(total_size));
+    if medias.is_empty() {
+        return;
+    }
+    let mut video_codecs = Histogram::default();
+    let mut audio_codecs = Histogram::default();
+    let mut containers = Histogram::default();
+    let mut resolutions = Histogram::default();
+    let mut subtitle_languages = Histogram::default();
+    let mut dynamic_ranges = Histogram::default();
+    let mut profile = Histogram::default();
+    for media in medias {
+        media.video_codecs.iter().for_each(|codec| video_codecs.add(codec, media.size));
+        media.audio_codecs.iter().for_each(|codec| audio_codecs.add(codec, media.size));
+        containers.add(&media.container, media.size);
+        resolutions.add(&media.resolution, media.size);
+        media.subtitle_languages.iter().for_each(|language| subtitle_languages.add(language, media.size));
+        dynamic_ranges.add(&media.dynamic_range, media.size);
+        profile.add(if media.failing { "failing" } else { "compatible" }, media.size);
+    }
+    print_histogram("video codecs", &video_codecs, medias.len());
+    print_histogram("audio codecs", &audio_codecs, medias.len());
+    print_histogram("containers", &containers, medias.len());
+    print_histogram("resolutions", &resolutions, medias.len());
+    print_histogram("subtitle languages", &subtitle_languages, medias.len());
+    print_histogram("dynamic range", &dynamic_ranges, medias.len());
+    print_histogram("active profile", &profile, medias.len());
+}
+
+fn collect_stats(medias: &[PathBuf], jobs: usize, prober: &dyn Prober, profile: &Profile, errors: &ErrorLog) -> Result<Vec<MediaStats>, LadonError> {
+    let checks = check_medias(medias, jobs, errors, |media_location| media_stats(media_location, prober, profile))?;
+    return Ok(checks.into_iter().map(|(_, stats)| stats).collect());
+}
+
+/// Prints histograms of the medias of a directory, and how many of them fail the profile in use.
+pub fn print_library_stats(directory_location: &Path, options: StatsOptions, prober: &dyn Prober, errors: &ErrorLog) -> Result<(), LadonError> {
+    println!("stats directory: {directory_location:?}");
+    validate_directory(directory_location)?;
+    let profile = select_profile(options.profile_location, options.target_name)?;
+    let mut medias = Vec::new();
+    collect_medias(directory_location, &MediaDetector::new(options.extensions, options.sniff), errors, &mut medias)?;
+    medias.sort_by(|media, other_media| media.as_os_str().cmp(other_media.as_os_str()));
+    let media_stats = match &options.cache_location {
+        Some(cache_location) => {
+            let cached_prober = CachedProber::new(prober, cache_location, options.rebuild_cache);
+            let collected = collect_stats(&medias, options.jobs, &cached_prober, &profile, errors);
+            cached_prober.save();
+            collected
+        },
+        None => collect_stats(&medias, options.jobs, prober, &profile, errors),
+    }?;
+    print_stats(&media_stats);
+    return Ok(());
+}
@@ -0,0 +1,23 @@
+use crate::errors::LadonError;
+use crate::profile::{Profile, parse_profile};
+
+pub const TARGET_NAMES: [&str; 4] = ["chromecast", "apple-tv", "roku", "web-browser"];
+
+fn target_definition(target_name: &str) -> Result<&'static str, LadonError> {
+    return match target_name {
+        "chromecast" => Ok(include_str!("../profiles/targets/chromecast.toml")),
+        "apple-tv" => Ok(include_str!("../profiles/targets/apple-tv.toml")),
+        "roku" => Ok(include_str!("../profiles/targets/roku.toml")),
+        "web-browser" => Ok(include_str!("../profiles/targets/web-browser.toml")),
+        _ => Err(LadonError::Profile {
+            name: target_name.to_string(),
+            reason: format!("unknown target, available targets : {}", TARGET_NAMES.join(", ")),
+        }),
+    };
+}
+
+/// Built-in profile describing what a device can direct-play.
+/// Every target sets an empty subtitle `languages` list : subtitle languages are a library choice, not a device limit.
+pub fn target_profile(target_name: &str) -> Result<Profile, LadonError> {
+    return parse_profile(target_name, target_definition(target_name)?);
+}
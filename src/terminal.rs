@@ -13,18 +13,18 @@ impl Counter {
 
     fn new(start: usize, end: usize) -> Counter {
         Counter {
-            start: start,
-            end: end,
-            index: start.clone(),
+            start,
+            end,
+            index: start,
         }
     }
 
     fn incr(&mut self) -> usize {
-        return if(self.index + 1 <= self.end) { self.index += 1; self.index } else { panic!("Counter reach end limit!") };
+        return if(self.index < self.end) { self.index += 1; self.index } else { panic!("Counter reach end limit!") };
     }
 
     fn decr(&mut self) -> usize {
-        return if(self.index - 1 >= self.start) { self.index -= 1; self.index } else { panic!("Counter reach start limit!") };
+        return if(self.index > self.start) { self.index -= 1; self.index } else { panic!("Counter reach start limit!") };
     }
 }
 
@@ -1,15 +1,13 @@
 pub mod validation {
 
-    use std::path::{Path, PathBuf};
-
-    fn is_existing_directory(directory_location: &str) -> bool {
-        return Path::new(directory_location).is_dir();
-    }
+    use crate::errors::LadonError;
+    use std::path::Path;
 
-    pub fn validate_directory(directory_location: &str) {
-        if !is_existing_directory(directory_location) { 
-            panic!("{} is not a valid directory path!", directory_location);
+    pub fn validate_directory(directory_location: &Path) -> Result<(), LadonError> {
+        if !directory_location.is_dir() { 
+            return Err(LadonError::NoInput { path: directory_location.to_path_buf(), reason: "is not a valid directory path" });
         }
+        return Ok(());
     }
 
     fn is_valid_parent_path(path: &Path) -> bool {
@@ -19,143 +17,304 @@ pub mod validation {
         };
     }
 
-    pub fn validate_output_file(output_file_location: &str) {
-        let output_file_path = Path::new(output_file_location);
-        if output_file_path.exists() {
-            if !output_file_path.is_file() { 
-                panic!("{output_file_location} is not a file!"); 
+    pub fn validate_output_file(output_file_location: &Path) -> Result<(), LadonError> {
+        let path = output_file_location.to_path_buf();
+        if output_file_location.exists() {
+            if !output_file_location.is_file() { 
+                return Err(LadonError::CannotCreate { path, reason: "is not a file" });
             }
-        } else if !is_valid_parent_path(output_file_path) { 
-            panic!("{output_file_location} is not a valid file path!"); 
+        } else if !is_valid_parent_path(output_file_location) { 
+            return Err(LadonError::CannotCreate { path, reason: "is not a valid file path" });
         }
+        return Ok(());
     }
 
-    pub fn validate_input_file(input_file_location: &str) {
-        let input_file_path = Path::new(input_file_location);
-        if input_file_path.exists() {
-            if !input_file_path.is_file() { 
-                panic!("{input_file_location} is not a file!"); 
+    pub fn validate_input_file(input_file_location: &Path) -> Result<(), LadonError> {
+        let path = input_file_location.to_path_buf();
+        if input_file_location.exists() {
+            if !input_file_location.is_file() { 
+                return Err(LadonError::NoInput { path, reason: "is not a file" });
             }
         } else { 
-            panic!("{input_file_location} does not exists!"); 
+            return Err(LadonError::NoInput { path, reason: "does not exist" });
         }
+        return Ok(());
     }
 
 }
 
 pub mod path {
+    use crate::errors::LadonError;
     use std::fs::{read_dir, DirEntry, FileType};
-    use core::convert::AsRef;
+    use std::path::{Path, PathBuf};
 
-    pub fn directory_entries<'a>(directory_location: &'a str) -> impl Iterator<Item = DirEntry> + 'a{
+    /// Entries of a directory, an entry that cannot be read does not hide the following ones.
+    pub fn directory_entries(directory_location: &Path) -> Result<impl Iterator<Item = Result<DirEntry, LadonError>> + '_, LadonError> {
         return match read_dir(directory_location) {
-            Ok(reader) => reader.map(|result| match result {
-                Ok(entry) => entry,
-                Err(e) => panic!("Error reading directory entry : {e:?}")
-            }),
-            Err(e) => panic!("Error reading directory entries from {directory_location:?} : {e:?}"),
+            Ok(reader) => Ok(reader.map(move |result| {
+                result.map_err(|e| LadonError::io("reading an entry of", directory_location, e))
+            })),
+            Err(e) => Err(LadonError::io("reading directory", directory_location, e)),
         }
     }
 
-    pub fn location_of(entry: &DirEntry) -> String {
-        let entry_path_buf = entry.path();
-        let entry_path_os = entry_path_buf.as_os_str();
-        return match entry_path_os.to_str() {
-            Some(entry_location) => entry_location.to_string(),
-            None => panic!("Cannot convert path {entry_path_os:?} to str")
-        };
+    /// Path of a directory entry, kept as the OS gives it even when it is not valid UTF-8.
+    pub fn location_of(entry: &DirEntry) -> PathBuf {
+        return entry.path();
     }
     
-    pub fn type_of(entry: &DirEntry, entry_location: &str) -> FileType {
-        return match entry.file_type() {
-            Ok(file_type) => file_type,
-            Err(e) => panic!("Error reading {entry_location:?} file type : {e:?}")
-        }
+    pub fn type_of(entry: &DirEntry, entry_location: &Path) -> Result<FileType, LadonError> {
+        return entry.file_type().map_err(|e| LadonError::io("reading file type of", entry_location, e));
     }
 
 }
 
 pub mod file {
-    use std::io::{Result, BufRead, BufReader};
-    use std::fs::{File, remove_file, rename};
-    use std::path::Path;
-    use substring::Substring;
+    use crate::errors::LadonError;
+    use std::ffi::{OsStr, OsString};
+    use std::fs::{remove_file, rename};
+    use std::path::{Path, PathBuf};
 
-    pub fn file_reader(path: &str) -> BufReader<File> {
-        return match File::open(path) {
-            Ok(file) => BufReader::new(file),
-            Err(e) => panic!("Could not open File Reader for {path}!"),
+    pub fn file_name(location: &Path) -> &OsStr {
+        return match location.file_name() {
+            Some(file_name) => file_name,
+            None => panic!("Cannot get file name from path {location:?}")
         }
     }
 
-    pub fn file_name(location: &str) -> &str {
-        let file_path = Path::new(location);
-        return match file_path.file_name() {
-            Some(os_path) => match os_path.to_str() {
-                Some(file_name) => file_name,
-                None => panic!("Cannot get file name from OS path '{location}'")
-            },
-            None => panic!("Cannot get file name from path '{location}'")
+    pub fn file_directory(file_location: &Path) -> &Path {
+        return match file_location.parent() {
+            Some(directory) => directory,
+            None => panic!("Cannot access directory for file {file_location:?}!")
         }
     }
 
-    pub fn file_directory(file_location: &str) -> &str {
-        return match Path::new(file_location).parent() {
-            Some(directory) => match directory.to_str() {
-                Some(directory_path) => directory_path,
-                None => panic!("Cannot convert directory path to String for {file_location}")
-            },
-            None => panic!("Cannot access directory for file {file_location}!")
+    pub fn without_ext(file_name: &OsStr) -> &OsStr {
+        return match Path::new(file_name).file_stem() {
+            Some(stem) => stem,
+            None => file_name
         }
     }
 
-    pub fn without_ext(file_name: &str) -> &str {
-        return match file_name.rfind('.') {
-            Some(dot_index) => file_name.substring(0, dot_index),
-            None => file_name
+    pub fn delete(file_location: &Path) -> Result<(), LadonError> {
+        return remove_file(file_location).map_err(|e| LadonError::io("deleting", file_location, e));
+    }
+
+    pub fn create_backup(file_location: &Path) -> Result<PathBuf, LadonError> {
+        let mut file_backup_location = OsString::from(file_location);
+        file_backup_location.push(".bckp");
+        let file_backup_location = PathBuf::from(file_backup_location);
+        return match rename(file_location, &file_backup_location) {
+            Ok(_) => Ok(file_backup_location),
+            Err(e) => Err(LadonError::io("creating a backup of", file_location, e)),
         }
     }
-    
-    fn to_line(result: Result<String>) -> String {
-        return match result {
-            Ok(line) => line,
-            Err(e) => panic!("Error occured reading line from file : {e:?}"),
+}
+
+/// Media lists written by `search` and read by `export`, carrying any path the OS can give.
+pub mod list {
+    use crate::errors::LadonError;
+    use std::borrow::Cow;
+    use std::fs::{read, File};
+    use std::io::{BufWriter, Write};
+    use std::path::{Path, PathBuf};
+
+    #[derive(Clone, Copy, clap::ValueEnum)]
+    pub enum ListFormat {
+        /// One path per line, backslashes and bytes that are not printable UTF-8 escaped as `\\` and `\xNN`
+        Escaped,
+        /// Raw paths separated by NUL bytes, like `find -print0`
+        Nul,
+    }
+
+    #[cfg(unix)]
+    fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+        use std::os::unix::ffi::OsStrExt;
+        return Cow::Borrowed(path.as_os_str().as_bytes());
+    }
+
+    #[cfg(not(unix))]
+    fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+        return Cow::Owned(path.to_string_lossy().into_owned().into_bytes());
+    }
+
+    #[cfg(unix)]
+    fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+        use std::os::unix::ffi::OsStringExt;
+        return PathBuf::from(std::ffi::OsString::from_vec(bytes));
+    }
+
+    #[cfg(not(unix))]
+    fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+        return PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+    }
+
+    fn escape_bytes(escaped_path: &mut String, bytes: &[u8]) {
+        for byte in bytes {
+            escaped_path.push_str(&format!("\\x{byte:02x}"));
         }
     }
-    
-    pub fn read_lines(file_location: &str) -> impl Iterator<Item=String> {
-        return file_reader(file_location).lines()
-        .map(to_line); 
+
+    /// Text form of a path that can be turned back into the same path : UTF-8 paths without
+    /// backslashes or control characters are left as they are.
+    pub fn escaped(path: &Path) -> String {
+        let mut escaped_path = String::new();
+        for chunk in path_bytes(path).utf8_chunks() {
+            for character in chunk.valid().chars() {
+                if character == '\\' {
+                    escaped_path.push_str("\\\\");
+                } else if character.is_control() {
+                    escape_bytes(&mut escaped_path, character.encode_utf8(&mut [0; 4]).as_bytes());
+                } else {
+                    escaped_path.push(character);
+                }
+            }
+            escape_bytes(&mut escaped_path, chunk.invalid());
+        }
+        return escaped_path;
     }
 
-    pub fn delete(file_location: &str) {
-        match remove_file(file_location) {
-            Ok(_) => (),
-            Err(e) => panic!("Error deleting {file_location}: {e:?}")
+    fn hex_digit(digit: u8) -> Option<u8> {
+        return (digit as char).to_digit(16).map(|value| value as u8);
+    }
+
+    /// Path of an escaped line, None when an escape sequence is malformed.
+    /// Unescaped bytes are taken as they are, so lists of raw paths without backslashes are read too.
+    pub fn unescaped(line: &[u8]) -> Option<PathBuf> {
+        let mut bytes = Vec::with_capacity(line.len());
+        let mut remaining = line.iter();
+        while let Some(&byte) = remaining.next() {
+            if byte != b'\\' {
+                bytes.push(byte);
+                continue;
+            }
+            match remaining.next()? {
+                b'\\' => bytes.push(b'\\'),
+                b'x' => {
+                    let high = hex_digit(*remaining.next()?)?;
+                    let low = hex_digit(*remaining.next()?)?;
+                    bytes.push(high << 4 | low);
+                },
+                _ => return None,
+            }
         }
+        return Some(path_from_bytes(bytes));
     }
 
-    pub fn create_backup(file_location: &str) -> String {
-        let file_backup_location = format!("{file_location}.bckp");
-        return match rename(file_location, &file_backup_location) {
-            Ok(_) => file_backup_location,
-            Err(e) => panic!("Error Creating backup '{file_backup_location}' for '{file_location}' : {e:?}")
+    pub fn write_list(list_location: &Path, paths: &[&Path], format: ListFormat) -> Result<(), LadonError> {
+        let list_file = File::create(list_location).map_err(|e| LadonError::io("creating", list_location, e))?;
+        let mut list_writer = BufWriter::new(list_file);
+        for path in paths {
+            let written = match format {
+                ListFormat::Escaped => writeln!(list_writer, "{}", escaped(path)),
+                ListFormat::Nul => list_writer.write_all(&path_bytes(path)).and_then(|_| list_writer.write_all(b"\0")),
+            };
+            written.map_err(|e| LadonError::io("writing", list_location, e))?;
         }
+        return list_writer.flush().map_err(|e| LadonError::io("writing", list_location, e));
+    }
+
+    fn line_path(line_number: usize, line: &[u8], list_location: &Path) -> Result<PathBuf, LadonError> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        return match unescaped(line) {
+            Some(path) => Ok(path),
+            None => Err(LadonError::InvalidList { path: list_location.to_path_buf(), line: line_number + 1 }),
+        };
     }
+
+    /// Paths of a list, empty entries are ignored. A malformed line only fails its own entry.
+    pub fn read_list(list_location: &Path, format: ListFormat) -> Result<Vec<Result<PathBuf, LadonError>>, LadonError> {
+        let content = read(list_location).map_err(|e| LadonError::io("reading list", list_location, e))?;
+        return Ok(match format {
+            ListFormat::Escaped => content.split(|byte| *byte == b'\n')
+            .enumerate()
+            .filter(|(_, line)| !line.is_empty() && *line != b"\r")
+            .map(|(line_number, line)| line_path(line_number, line, list_location))
+            .collect(),
+            ListFormat::Nul => content.split(|byte| *byte == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| Ok(path_from_bytes(entry.to_vec())))
+            .collect(),
+        });
+    }
+
 }
 
 pub mod media {
+    use std::fs::File;
+    use std::io::Read;
+    use std::path::Path;
 
-    pub fn is_media(media_location: &str) -> bool {
-        return media_location.ends_with(".avi") || media_location.ends_with(".mp4") || media_location.ends_with(".mkv");
+    pub const MEDIA_EXTENSIONS: [&str; 13] = ["avi", "mp4", "mkv", "m4v", "mov", "wmv", "ts", "m2ts", "webm", "flv", "mpg", "mpeg", "vob"];
+
+    /// Lowercase extension of a file, `MKV` and `mkv` are the same extension.
+    pub fn extension_of(location: &Path) -> Option<String> {
+        return location.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
     }
 
-    pub fn is_avi_file(media_location: &str) -> bool {
-        return media_location.ends_with(".avi");
+    fn has_extension(location: &Path, extensions: &[String]) -> bool {
+        return match extension_of(location) {
+            Some(extension) => extensions.iter().any(|media_extension| media_extension.eq_ignore_ascii_case(&extension)),
+            None => false,
+        };
     }
-    
-    pub fn is_non_avi_video_file(media_location: &str) -> bool {
-        return media_location.ends_with(".mp4") || media_location.ends_with(".mkv");
+
+    pub fn default_extensions() -> Vec<String> {
+        return MEDIA_EXTENSIONS.iter().map(|extension| extension.to_string()).collect();
     }
 
-}
\ No newline at end of file
+    /// Enough bytes to find two MPEG-TS sync bytes in a row, with 192 bytes M2TS packets.
+    const SNIFF_LENGTH: usize = 4 + 192 + 1;
+
+    fn is_transport_stream(header: &[u8], first_sync: usize, packet_size: usize) -> bool {
+        return header.len() > first_sync + packet_size && header[first_sync] == 0x47 && header[first_sync + packet_size] == 0x47;
+    }
+
+    /// Container family recognized from the first bytes of a file, named like its usual extension.
+    pub fn sniff_container(location: &Path) -> Option<&'static str> {
+        let mut header = Vec::with_capacity(SNIFF_LENGTH);
+        let file = File::open(location).ok()?;
+        file.take(SNIFF_LENGTH as u64).read_to_end(&mut header).ok()?;
+        return if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            Some("mkv")
+        } else if header.len() >= 8 && matches!(&header[4..8], b"ftyp" | b"moov" | b"mdat" | b"wide" | b"free") {
+            Some("mp4")
+        } else if header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"AVI " {
+            Some("avi")
+        } else if header.starts_with(&[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11]) {
+            Some("wmv")
+        } else if header.starts_with(b"FLV") {
+            Some("flv")
+        } else if header.starts_with(&[0x00, 0x00, 0x01, 0xBA]) {
+            Some("mpg")
+        } else if is_transport_stream(&header, 0, 188) {
+            Some("ts")
+        } else if is_transport_stream(&header, 4, 192) {
+            Some("m2ts")
+        } else {
+            None
+        };
+    }
+
+    /// Tells medias apart from other files, by extension and optionally by content.
+    pub struct MediaDetector {
+        extensions: Vec<String>,
+        sniff: bool,
+    }
+
+    impl MediaDetector {
+
+        /// When `sniff` is set, files without a media extension are recognized by their first bytes.
+        pub fn new(extensions: Vec<String>, sniff: bool) -> MediaDetector {
+            MediaDetector { extensions, sniff }
+        }
+
+        pub fn is_media(&self, location: &Path) -> bool {
+            return has_extension(location, &self.extensions) || (self.sniff && sniff_container(location).is_some());
+        }
+
+    }
+
+}
@@ -0,0 +1,130 @@
+use crate::errors::{ErrorLog, LadonError};
+use crate::ffprobe::{Probe, ProbeError};
+use crate::ffprobe::probers::Prober;
+use crate::ffprobe::streams::StreamClass;
+use crate::ffmpeg::decoders::Decoder;
+use crate::report::write_damage_report;
+use crate::search::{collect_medias, check_medias};
+use crate::utils::validation::{validate_directory, validate_output_file};
+use crate::utils::media::MediaDetector;
+use crate::utils::list::{ListFormat, write_list};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Medias smaller than this share of the size announced by their duration and bit rates are truncated.
+const TRUNCATED_SIZE_RATIO: f64 = 0.9;
+
+const MEGABYTE: f64 = 1_000_000.0;
+
+/// Check that found a media damaged.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// ffprobe cannot read the media
+    Unreadable,
+    /// The media is smaller than its duration and bit rates announce
+    Truncated,
+    /// ffmpeg met errors while decoding the media
+    Decode,
+}
+
+/// One reason why a media is damaged.
+#[derive(Serialize)]
+pub struct Damage {
+    check: Check,
+    message: String,
+}
+
+impl Damage {
+
+    fn new(check: Check, message: String) -> Damage {
+        Damage { check, message }
+    }
+
+    pub fn get_check(&self) -> Check {
+        return self.check;
+    }
+
+    pub fn get_message(&self) -> &String {
+        return &self.message;
+    }
+
+}
+
+pub struct VerifyOptions {
+    pub jobs: usize,
+    /// Only probe the medias, without the slow ffmpeg decoding pass
+    pub quick: bool,
+    pub extensions: Vec<String>,
+    pub sniff: bool,
+    pub list_format: ListFormat,
+}
+
+struct Verify<'a> {
+    prober: &'a dyn Prober,
+    decoder: &'a dyn Decoder,
+    quick: bool,
+}
+
+/// Size announced by the media duration and the bit rates of its video and audio streams,
+/// unknown as soon as one of those streams does not tell its bit rate.
+fn expected_size(probe: &Probe) -> Option<f64> {
+    let duration = probe.format()?.get_duration()?;
+    let bit_rates: Option<Vec<u64>> = probe.streams().iter()
+    .filter(|stream| matches!(stream.class(), StreamClass::Video | StreamClass::Audio))
+    .map(|stream| stream.get_bit_rate())
+    .collect();
+    let bit_rate: u64 = bit_rates?.iter().sum();
+    return if bit_rate == 0 { None } else { Some(bit_rate as f64 * duration / 8.0) };
+}
+
+fn size_damage(probe: &Probe) -> Option<Damage> {
+    let size = probe.format()?.get_size()? as f64;
+    let expected_size = expected_size(probe)?;
+    return if size < expected_size * TRUNCATED_SIZE_RATIO {
+        Some(Damage::new(Check::Truncated, format!(
+            "size {:.0} MB is {:.0}% of the {:.0} MB expected from duration and bit rates",
+            size / MEGABYTE, size * 100.0 / expected_size, expected_size / MEGABYTE,
+        )))
+    } else {
+        None
+    };
+}
+
+/// An unreadable media is not decoded, its probe error says enough.
+fn media_damages(media_location: &Path, verify: &Verify) -> Result<Vec<Damage>, LadonError> {
+    let mut damages = Vec::new();
+    match verify.prober.probe(media_location) {
+        Ok(probe) => damages.extend(size_damage(&probe)),
+        Err(source @ (ProbeError::Unreadable { .. } | ProbeError::InvalidOutput(_))) => {
+            damages.push(Damage::new(Check::Unreadable, source.to_string()));
+            return Ok(damages);
+        },
+        Err(source) => return Err(LadonError::Probe { path: media_location.to_path_buf(), source }),
+    }
+    if !verify.quick {
+        let decoder_errors = verify.decoder.decode_errors(media_location)
+        .map_err(|source| LadonError::Decode { path: media_location.to_path_buf(), source })?;
+        damages.extend(decoder_errors.into_iter().map(|message| Damage::new(Check::Decode, message)));
+    }
+    return Ok(damages);
+}
+
+/// Lists the damaged medias of a directory, the reasons are written to a report next to the list.
+pub fn verify_medias(directory_location: &Path, output_file_location: &Path, options: VerifyOptions, prober: &dyn Prober, decoder: &dyn Decoder, errors: &ErrorLog) -> Result<(), LadonError> {
+    println!("verify directory: {directory_location:?}, write output: {output_file_location:?}");
+    validate_directory(directory_location)?;
+    validate_output_file(output_file_location)?;
+    let verify = Verify { prober, decoder, quick: options.quick };
+    let mut medias = Vec::new();
+    collect_medias(directory_location, &MediaDetector::new(options.extensions, options.sniff), errors, &mut medias)?;
+    medias.sort_by(|media, other_media| media.as_os_str().cmp(other_media.as_os_str()));
+    let checks = check_medias(&medias, options.jobs, errors, |media_location| media_damages(media_location, &verify))?;
+    let damaged_medias: Vec<(&PathBuf, Vec<Damage>)> = checks.into_iter()
+    .filter(|(_, damages)| !damages.is_empty())
+    .map(|(index, damages)| (&medias[index], damages))
+    .collect();
+    let media_locations: Vec<&Path> = damaged_medias.iter().map(|(media_location, _)| media_location.as_path()).collect();
+    write_list(output_file_location, &media_locations, options.list_format)?;
+    return write_damage_report(output_file_location, &damaged_medias);
+}
@@ -0,0 +1,74 @@
+#![allow(dead_code, clippy::needless_return)]
+
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+pub fn fixtures_directory() -> PathBuf {
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures");
+}
+
+/// Runs ladon with ffprobe replaced by the recorded outputs of `tests/fixtures`.
+pub fn ladon(args: &[&str]) -> Output {
+    return ladon_with_fixtures(args, &fixtures_directory());
+}
+
+pub fn ladon_with_fixtures(args: &[&str], fixtures_directory: &Path) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_ladon"))
+    .args(args)
+    .arg("--probe-fixtures")
+    .arg(fixtures_directory)
+    .output();
+    return match output {
+        Ok(output) => output,
+        Err(e) => panic!("Cannot run ladon : {e:?}"),
+    };
+}
+
+pub fn stderr_of(output: &Output) -> String {
+    return String::from_utf8_lossy(&output.stderr).to_string();
+}
+
+/// Temporary directory filled with empty files, media content is described by the fixtures.
+pub fn tree(files: &[&str]) -> TempDir {
+    let directory = TempDir::new().expect("Cannot create temporary directory");
+    for file in files {
+        write_file(&directory.path().join(file), file);
+    }
+    return directory;
+}
+
+pub fn write_file(path: &Path, content: &str) {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent).expect("Cannot create parent directory");
+    }
+    write(path, content).expect("Cannot write file");
+}
+
+pub fn location(directory: &TempDir, file: &str) -> String {
+    return directory.path().join(file).to_string_lossy().to_string();
+}
+
+pub fn read(path: &Path) -> String {
+    return read_to_string(path).expect("Cannot read file");
+}
+
+/// Sorted lines of a list file, relative to `directory`.
+pub fn relative_lines(list_path: &Path, directory: &TempDir) -> Vec<String> {
+    let prefix = format!("{}/", directory.path().to_string_lossy());
+    let mut lines: Vec<String> = read(list_path).lines()
+    .map(|line| line.trim_start_matches(&prefix).to_string())
+    .collect();
+    lines.sort();
+    return lines;
+}
+
+/// `café` encoded in Latin-1, a file name that is not valid UTF-8.
+#[cfg(unix)]
+pub fn latin1_name(extension: &str) -> std::ffi::OsString {
+    use std::os::unix::ffi::OsStringExt;
+    let mut name = b"caf\xe9.".to_vec();
+    name.extend_from_slice(extension.as_bytes());
+    return std::ffi::OsString::from_vec(name);
+}
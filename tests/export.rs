@@ -0,0 +1,107 @@
+mod common;
+
+use common::{ladon, location, read, stderr_of, tree, write_file};
+use tempfile::TempDir;
+
+#[test]
+fn copies_listed_medias_to_export_directory() {
+    let library = tree(&["a/first.mkv", "b/second.mp4", "c/ignored.mkv"]);
+    let list = library.path().join("list.txt");
+    write_file(&list, &format!("{}\n{}\n", location(&library, "a/first.mkv"), location(&library, "b/second.mp4")));
+    let export_directory = TempDir::new().unwrap();
+    let output = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &export_directory.path().to_string_lossy()]);
+    assert!(output.status.success(), "export failed : {}", stderr_of(&output));
+    assert_eq!(read(&export_directory.path().join("first.mkv")), "a/first.mkv");
+    assert_eq!(read(&export_directory.path().join("second.mp4")), "b/second.mp4");
+    assert!(!export_directory.path().join("ignored.mkv").exists());
+}
+
+#[test]
+fn rejects_list_with_non_media_files() {
+    let library = tree(&["notes.txt"]);
+    let list = library.path().join("list.txt");
+    write_file(&list, &format!("{}\n", location(&library, "notes.txt")));
+    let export_directory = TempDir::new().unwrap();
+    let output = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &export_directory.path().to_string_lossy()]);
+    assert!(!output.status.success());
+    assert!(!export_directory.path().join("notes.txt").exists());
+}
+
+#[test]
+fn rejects_missing_export_directory() {
+    let library = tree(&["first.mkv"]);
+    let list = library.path().join("list.txt");
+    write_file(&list, &format!("{}\n", location(&library, "first.mkv")));
+    let output = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &location(&library, "missing")]);
+    assert!(!output.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn reads_escaped_and_nul_separated_lists() {
+    use std::os::unix::ffi::OsStrExt;
+    let library = tree(&["back\\slash.mkv"]);
+    let media_name = common::latin1_name("mkv");
+    write_file(&library.path().join(&media_name), "latin1");
+    let escaped_list = library.path().join("list.txt");
+    write_file(&escaped_list, &format!("{}\n{}\n", location(&library, "caf\\xe9.mkv"), location(&library, "back\\\\slash.mkv")));
+    let escaped_export = TempDir::new().unwrap();
+    let output = ladon(&["export", "-m", &escaped_list.to_string_lossy(), "-e", &escaped_export.path().to_string_lossy()]);
+    assert!(output.status.success(), "export failed : {}", stderr_of(&output));
+    assert_eq!(read(&escaped_export.path().join(&media_name)), "latin1");
+    assert_eq!(read(&escaped_export.path().join("back\\slash.mkv")), "back\\slash.mkv");
+
+    let nul_list = library.path().join("list.nul");
+    let mut content = library.path().join(&media_name).as_os_str().as_bytes().to_vec();
+    content.push(0);
+    std::fs::write(&nul_list, content).unwrap();
+    let nul_export = TempDir::new().unwrap();
+    let output = ladon(&["export", "-m", &nul_list.to_string_lossy(), "-e", &nul_export.path().to_string_lossy(), "--list-format", "nul"]);
+    assert!(output.status.success(), "export failed : {}", stderr_of(&output));
+    assert_eq!(read(&nul_export.path().join(&media_name)), "latin1");
+}
+
+#[test]
+fn rejects_malformed_escape_sequences() {
+    let library = tree(&["first.mkv"]);
+    let list = library.path().join("list.txt");
+    write_file(&list, &format!("{}\\q\n", location(&library, "first.mkv")));
+    let export_directory = TempDir::new().unwrap();
+    let output = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &export_directory.path().to_string_lossy()]);
+    assert!(!output.status.success());
+    assert!(stderr_of(&output).contains("invalid escape sequence line 1"));
+}
+
+#[test]
+fn keeps_going_past_missing_medias() {
+    let library = tree(&["first.mkv"]);
+    let list = library.path().join("list.txt");
+    write_file(&list, &format!("{}\n{}\n", location(&library, "missing.mkv"), location(&library, "first.mkv")));
+    let export_directory = TempDir::new().unwrap();
+    let export_location = export_directory.path().to_string_lossy().to_string();
+    let stopped = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &export_location]);
+    assert_eq!(stopped.status.code(), Some(66));
+    assert!(!export_directory.path().join("first.mkv").exists());
+    let kept_going = ladon(&["export", "-m", &list.to_string_lossy(), "-e", &export_location, "--keep-going"]);
+    assert_eq!(kept_going.status.code(), Some(66));
+    assert!(stderr_of(&kept_going).contains("1 files could not be processed :\n  - "));
+    assert_eq!(read(&export_directory.path().join("first.mkv")), "first.mkv");
+}
+
+#[test]
+fn exports_medias_found_by_sniffing_search() {
+    let library = tree(&[]);
+    std::fs::write(library.path().join("no_extension"), [0x1A, 0x45, 0xDF, 0xA3, 0x01, 0x00]).unwrap();
+    let list = library.path().join("list.txt");
+    let list_location = list.to_string_lossy().to_string();
+    let searched = ladon(&["search", "-m", &library.path().to_string_lossy(), "-o", &list_location, "--sniff", "--no-cache"]);
+    assert!(searched.status.success(), "search failed : {}", stderr_of(&searched));
+    assert_eq!(read(&list), format!("{}\n", location(&library, "no_extension")));
+    let export_directory = TempDir::new().unwrap();
+    let export_location = export_directory.path().to_string_lossy().to_string();
+    let rejected = ladon(&["export", "-m", &list_location, "-e", &export_location]);
+    assert_eq!(rejected.status.code(), Some(65));
+    let output = ladon(&["export", "-m", &list_location, "-e", &export_location, "--sniff"]);
+    assert!(output.status.success(), "export failed : {}", stderr_of(&output));
+    assert!(export_directory.path().join("no_extension").exists());
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::{ladon, read, stderr_of, tree};
+
+#[test]
+fn replaces_library_medias_with_transcoded_ones() {
+    let transcoded = tree(&["first.mkv", "second.mp4"]);
+    let library = tree(&["movies/first.avi", "shows/s01/second.mkv", "shows/s01/other.mkv"]);
+    let output = ladon(&["import", "-i", &transcoded.path().to_string_lossy(), "-t", &library.path().to_string_lossy()]);
+    assert!(output.status.success(), "import failed : {}", stderr_of(&output));
+    assert_eq!(read(&library.path().join("movies/first.mkv")), "first.mkv");
+    assert_eq!(read(&library.path().join("shows/s01/second.mp4")), "second.mp4");
+    assert!(!library.path().join("movies/first.avi").exists());
+    assert!(!library.path().join("movies/first.avi.bckp").exists());
+    assert!(!library.path().join("shows/s01/second.mkv").exists());
+    assert!(library.path().join("shows/s01/other.mkv").exists());
+}
+
+#[test]
+fn fails_when_a_media_has_no_match_in_library() {
+    let transcoded = tree(&["first.mkv", "unknown.mkv"]);
+    let library = tree(&["movies/first.avi"]);
+    let output = ladon(&["import", "-i", &transcoded.path().to_string_lossy(), "-t", &library.path().to_string_lossy()]);
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr_of(&output).contains("unknown.mkv has no media to replace"));
+}
+
+#[test]
+fn keeps_going_past_medias_that_cannot_be_imported() {
+    let transcoded = tree(&["first.mkv", "unknown.mkv", "lost.mkv"]);
+    let library = tree(&["movies/first.avi"]);
+    let output = ladon(&["import", "-i", &transcoded.path().to_string_lossy(), "-t", &library.path().to_string_lossy(), "--keep-going"]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(read(&library.path().join("movies/first.mkv")), "first.mkv");
+    let stderr = stderr_of(&output);
+    assert!(stderr.contains("2 files could not be processed :"));
+    assert!(stderr.contains("lost.mkv has no media to replace") && stderr.contains("unknown.mkv has no media to replace"));
+}
+
+#[cfg(unix)]
+#[test]
+fn matches_medias_whose_names_are_not_utf8() {
+    let transcoded = tree(&[]);
+    common::write_file(&transcoded.path().join(common::latin1_name("mkv")), "transcoded");
+    let library = tree(&["movies/other.avi"]);
+    common::write_file(&library.path().join("movies").join(common::latin1_name("avi")), "original");
+    let output = ladon(&["import", "-i", &transcoded.path().to_string_lossy(), "-t", &library.path().to_string_lossy()]);
+    assert!(output.status.success(), "import failed : {}", stderr_of(&output));
+    assert_eq!(read(&library.path().join("movies").join(common::latin1_name("mkv"))), "transcoded");
+    assert!(!library.path().join("movies").join(common::latin1_name("avi")).exists());
+}
+
+#[test]
+fn replaces_medias_with_the_extensions_given_to_search() {
+    let transcoded = tree(&["first.mkv"]);
+    let library = tree(&["music/first.m4a"]);
+    let transcoded_location = transcoded.path().to_string_lossy().to_string();
+    let library_location = library.path().to_string_lossy().to_string();
+    let output = ladon(&["import", "-i", &transcoded_location, "-t", &library_location]);
+    assert_eq!(output.status.code(), Some(65));
+    let output = ladon(&["import", "-i", &transcoded_location, "-t", &library_location, "--extensions", "mkv,m4a"]);
+    assert!(output.status.success(), "import failed : {}", stderr_of(&output));
+    assert_eq!(read(&library.path().join("music/first.mkv")), "first.mkv");
+    assert!(!library.path().join("music/first.m4a").exists());
+}
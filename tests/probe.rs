@@ -0,0 +1,57 @@
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{ladon, location, tree};
+
+fn stdout_of(output: &std::process::Output) -> String {
+    return String::from_utf8_lossy(&output.stdout).to_string();
+}
+
+#[test]
+fn prints_streams_and_failures() {
+    let library = tree(&["h264_dts.mkv"]);
+    let output = ladon(&["probe", &location(&library, "h264_dts.mkv")]);
+    assert!(output.status.success());
+    let stdout = stdout_of(&output);
+    assert!(stdout.contains("container : matroska,webm"));
+    assert!(stdout.contains("1  audio  dts    DTS      eng       default"));
+    assert!(stdout.contains("verdict : needs transcoding\n  - audio #1 dts not in [aac]\n"));
+}
+
+#[test]
+fn uses_active_target_rules() {
+    let library = tree(&["h264_level51.mp4"]);
+    let media_location = location(&library, "h264_level51.mp4");
+    assert!(stdout_of(&ladon(&["probe", &media_location])).contains("verdict : compatible"));
+    let chromecast = stdout_of(&ladon(&["explain", &media_location, "--target", "chromecast"]));
+    assert!(chromecast.contains("  - video #0 h264 level 5.1 above 4.1"));
+}
+
+#[test]
+fn reports_unreadable_medias() {
+    let library = tree(&["broken.mkv"]);
+    let output = ladon(&["probe", &location(&library, "broken.mkv")]);
+    assert!(!output.status.success());
+    assert!(stdout_of(&output).contains("verdict : unreadable media"));
+}
+
+#[test]
+fn classifies_cover_art_and_attachments() {
+    let library = tree(&["h264_aac_poster.mkv"]);
+    let stdout = stdout_of(&ladon(&["probe", &location(&library, "h264_aac_poster.mkv")]));
+    assert!(stdout.contains("3  cover art   mjpeg"));
+    assert!(stdout.contains("4  attachment  ttf"));
+    assert!(stdout.contains("5  cover art   -"));
+    assert!(stdout.contains("verdict : compatible"));
+}
+
+#[test]
+fn recognizes_medias_like_search() {
+    let library = tree(&["h264_aac.mp4"]);
+    std::fs::write(library.path().join("no_extension"), [0x1A, 0x45, 0xDF, 0xA3, 0x01, 0x00]).unwrap();
+    let sniffed_location = location(&library, "no_extension");
+    assert_eq!(ladon(&["probe", &sniffed_location]).status.code(), Some(65));
+    assert!(ladon(&["probe", &sniffed_location, "--sniff"]).status.success());
+    assert_eq!(ladon(&["probe", &location(&library, "h264_aac.mp4"), "--extensions", "mkv"]).status.code(), Some(65));
+}
@@ -0,0 +1,471 @@
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{fixtures_directory, ladon, ladon_with_fixtures, location, read, relative_lines, stderr_of, tree, write_file};
+use std::path::Path;
+use std::process::Output;
+use tempfile::TempDir;
+
+fn run_search(library: &TempDir, output_file: &Path, extra_args: &[&str], fixtures: &Path) -> Output {
+    let output_file_location = output_file.to_string_lossy().to_string();
+    let library_location = library.path().to_string_lossy().to_string();
+    let mut args = vec!["search", "-m", &library_location, "-o", &output_file_location];
+    args.extend_from_slice(extra_args);
+    if !extra_args.contains(&"--cache") {
+        args.push("--no-cache");
+    }
+    return ladon_with_fixtures(&args, fixtures);
+}
+
+/// Recorded medias and the CSV report of a successful search.
+fn search_report(library: &TempDir, extra_args: &[&str]) -> (Vec<String>, String) {
+    let output_directory = TempDir::new().unwrap();
+    let output_file = output_directory.path().join("to_transcode.txt");
+    let output = run_search(library, &output_file, extra_args, &fixtures_directory());
+    assert!(output.status.success(), "search failed : {}", stderr_of(&output));
+    return (relative_lines(&output_file, library), read(&output_directory.path().join("to_transcode.csv")));
+}
+
+fn search(library: &TempDir, extra_args: &[&str]) -> Vec<String> {
+    return search_report(library, extra_args).0;
+}
+
+/// Recorded medias and the CSV report of a search following the rules of `profile`.
+fn search_with_profile(library: &TempDir, profile: &str) -> (Vec<String>, String) {
+    let profile_directory = TempDir::new().unwrap();
+    let profile_location = profile_directory.path().join("profile.toml");
+    write_file(&profile_location, profile);
+    return search_report(library, &["--profile", &profile_location.to_string_lossy()]);
+}
+
+#[test]
+fn records_compatible_medias_only_when_invalid() {
+    let library = tree(&["h264_aac.mp4", "h264_aac.mkv", "hevc_aac.mkv", "h264_dts.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["h264_dts.mkv", "hevc_aac.mkv"]);
+}
+
+#[test]
+fn records_unsupported_containers() {
+    let library = tree(&["old.avi", "h264_aac.mp4"]);
+    assert_eq!(search(&library, &[]), vec!["old.avi"]);
+}
+
+#[test]
+fn ignores_files_that_are_not_medias() {
+    let library = tree(&["notes.txt", "cover.jpg", "h264_aac.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+}
+
+#[test]
+fn searches_nested_directories() {
+    let library = tree(&["a/b/hevc_aac.mkv", "a/h264_aac.mp4", "c/h264_spa_subtitle.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["a/b/hevc_aac.mkv", "c/h264_spa_subtitle.mkv"]);
+}
+
+#[test]
+fn applies_profile_file_rules() {
+    let library = tree(&["hevc_aac.mkv", "h264_dts.mkv", "h264_aac.mp4"]);
+    let (recorded, _) = search_with_profile(&library, "[video]\ncodecs = [\"h264\", \"hevc\"]\n\n[container]\nformats = [\"mkv\"]\n");
+    assert_eq!(recorded, vec!["h264_aac.mp4", "h264_dts.mkv"]);
+}
+
+#[test]
+fn rejects_invalid_profile_file() {
+    let library = tree(&["h264_aac.mkv", "profile.toml"]);
+    write_file(&library.path().join("profile.toml"), "[video]\nunknown = 1\n");
+    let output_file_location = location(&library, "out.txt");
+    let library_location = library.path().to_string_lossy().to_string();
+    let profile_location = location(&library, "profile.toml");
+    let output = ladon(&["search", "-m", &library_location, "-o", &output_file_location, "-p", &profile_location]);
+    assert!(!output.status.success());
+
+    write_file(&library.path().join("profile.toml"), "[video.constraints.h264]\nmax_level = \"high\"\n");
+    let output = ladon(&["search", "-m", &library_location, "-o", &output_file_location, "-p", &profile_location, "--keep-going"]);
+    assert_eq!(output.status.code(), Some(78));
+    assert!(stderr_of(&output).contains("invalid h264 max_level \"high\""));
+
+    write_file(&library.path().join("profile.toml"), "[video.constraints.h264]\nmax_bit_depth = 8\n\n[video.constraints.H264]\nmax_bit_depth = 10\n");
+    let output = ladon(&["search", "-m", &library_location, "-o", &output_file_location, "-p", &profile_location]);
+    assert_eq!(output.status.code(), Some(78));
+    assert!(stderr_of(&output).contains("given twice"));
+}
+
+#[test]
+fn names_matroska_medias_webm_only_when_they_are() {
+    let library = tree(&["h264_mp3.mkv", "vp9_opus.mkv", "vp9_opus.webm"]);
+    let (recorded, csv) = search_report(&library, &["--target", "web-browser"]);
+    assert_eq!(recorded, vec!["h264_mp3.mkv"]);
+    assert!(csv.contains(",,,container,\"container mkv not in [mp4, webm]\""));
+}
+
+#[test]
+fn applies_target_rules() {
+    let library = tree(&["h264_aac.mp4", "h264_level51.mp4", "hevc_aac.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["hevc_aac.mkv"]);
+    assert_eq!(search(&library, &["--target", "chromecast"]), vec!["h264_level51.mp4", "hevc_aac.mkv"]);
+}
+
+#[test]
+fn targets_allow_subtitles_in_any_language() {
+    let library = tree(&["h264_aac.mkv", "h264_spa_subtitle.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["h264_spa_subtitle.mkv"]);
+    assert!(search(&library, &["--target", "chromecast"]).is_empty());
+    assert!(search(&library, &["--target", "roku"]).is_empty());
+    assert!(search_with_profile(&library, "[subtitle]\nlanguages = []\n").0.is_empty());
+}
+
+#[test]
+fn stops_on_unreadable_medias() {
+    let library = tree(&["broken.mkv", "hevc_aac.mkv"]);
+    let output_directory = TempDir::new().unwrap();
+    let output_file = output_directory.path().join("to_transcode.txt");
+    let output = run_search(&library, &output_file, &[], &fixtures_directory());
+    assert_eq!(output.status.code(), Some(65));
+    assert!(stderr_of(&output).contains("broken.mkv : unreadable media"));
+    assert!(!output_file.exists());
+}
+
+#[test]
+fn keeps_going_past_unreadable_medias() {
+    let library = tree(&["broken.mkv", "hevc_aac.mkv", "a/broken.mkv"]);
+    let output_directory = TempDir::new().unwrap();
+    let output_file = output_directory.path().join("to_transcode.txt");
+    let output = run_search(&library, &output_file, &["--keep-going", "--jobs", "2"], &fixtures_directory());
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(relative_lines(&output_file, &library), vec!["hevc_aac.mkv"]);
+    let stderr = stderr_of(&output);
+    assert!(stderr.contains("2 files could not be processed :"));
+    assert!(stderr.contains("a/broken.mkv : unreadable media"));
+}
+
+#[test]
+fn parallel_search_keeps_sorted_output() {
+    let library = tree(&["d/hevc_aac.mkv", "c/h264_dts.mkv", "b/h264_aac.mp4", "a/h264_spa_subtitle.mkv", "e/old.avi"]);
+    let expected = vec!["a/h264_spa_subtitle.mkv", "c/h264_dts.mkv", "d/hevc_aac.mkv", "e/old.avi"];
+    assert_eq!(search(&library, &[]), expected);
+    assert_eq!(search(&library, &["--jobs", "4"]), expected);
+}
+
+#[test]
+fn cached_probes_are_reused_until_media_changes() {
+    let library = tree(&["hevc_aac.mkv", "h264_aac.mp4"]);
+    let work_directory = TempDir::new().unwrap();
+    let output_file = work_directory.path().join("to_transcode.txt");
+    let cache = work_directory.path().join("cache").join("probes.json");
+    let cache_location = cache.to_string_lossy().to_string();
+    let no_fixtures = TempDir::new().unwrap();
+
+    let first = run_search(&library, &output_file, &["--cache", &cache_location], &fixtures_directory());
+    assert!(first.status.success(), "search failed : {}", stderr_of(&first));
+    assert!(cache.is_file());
+
+    let cached = run_search(&library, &output_file, &["--cache", &cache_location], no_fixtures.path());
+    assert!(cached.status.success(), "cached search probed again : {}", stderr_of(&cached));
+    assert_eq!(relative_lines(&output_file, &library), vec!["hevc_aac.mkv"]);
+
+    write_file(&library.path().join("h264_aac.mp4"), "modified media content");
+    let modified = run_search(&library, &output_file, &["--cache", &cache_location], no_fixtures.path());
+    assert!(!modified.status.success());
+    assert!(stderr_of(&modified).contains("h264_aac.mp4"));
+}
+
+#[test]
+fn rebuilt_cache_probes_every_media() {
+    let library = tree(&["hevc_aac.mkv"]);
+    let work_directory = TempDir::new().unwrap();
+    let output_file = work_directory.path().join("to_transcode.txt");
+    let cache_location = location(&work_directory, "probes.json");
+    let no_fixtures = TempDir::new().unwrap();
+
+    let first = run_search(&library, &output_file, &["--cache", &cache_location], &fixtures_directory());
+    assert!(first.status.success(), "search failed : {}", stderr_of(&first));
+    let rebuilt = run_search(&library, &output_file, &["--cache", &cache_location, "--rebuild-cache"], no_fixtures.path());
+    assert!(!rebuilt.status.success());
+}
+
+#[test]
+fn saved_cache_forgets_deleted_medias() {
+    let library = tree(&["hevc_aac.mkv", "h264_aac.mp4"]);
+    let work_directory = TempDir::new().unwrap();
+    let output_file = work_directory.path().join("to_transcode.txt");
+    let cache = work_directory.path().join("cache").join("probes.json");
+    let cache_location = cache.to_string_lossy().to_string();
+
+    let first = run_search(&library, &output_file, &["--cache", &cache_location], &fixtures_directory());
+    assert!(first.status.success(), "search failed : {}", stderr_of(&first));
+    assert!(read(&cache).contains("h264_aac.mp4"));
+    std::fs::remove_file(library.path().join("h264_aac.mp4")).unwrap();
+    let second = run_search(&library, &output_file, &["--cache", &cache_location], &fixtures_directory());
+    assert!(second.status.success(), "search failed : {}", stderr_of(&second));
+    assert!(!read(&cache).contains("h264_aac.mp4"));
+    assert!(read(&cache).contains("hevc_aac.mkv"));
+    let cache_files: Vec<_> = std::fs::read_dir(cache.parent().unwrap()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(cache_files, vec!["probes.json"]);
+
+    let blocked_location = location(&library, "hevc_aac.mkv/probes.json");
+    let blocked = run_search(&library, &output_file, &["--cache", &blocked_location], &fixtures_directory());
+    assert!(blocked.status.success(), "search failed : {}", stderr_of(&blocked));
+    assert!(stderr_of(&blocked).contains("creating probe cache directory"));
+}
+
+#[test]
+fn explains_why_medias_are_recorded() {
+    let library = tree(&["h264_dts.mkv", "old.avi", "h264_aac.mp4"]);
+    let work_directory = TempDir::new().unwrap();
+    let output_file = work_directory.path().join("to_transcode.txt");
+    let output = run_search(&library, &output_file, &[], &fixtures_directory());
+    assert!(output.status.success(), "search failed : {}", stderr_of(&output));
+
+    let jsonl = read(&work_directory.path().join("to_transcode.jsonl"));
+    let reports: Vec<serde_json::Value> = jsonl.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(reports.len(), 2);
+    assert!(reports[0]["path"].as_str().unwrap().ends_with("h264_dts.mkv"));
+    assert_eq!(reports[0]["failures"][0]["rule"], "audio_codec");
+    assert_eq!(reports[0]["failures"][0]["stream_index"], 1);
+    assert_eq!(reports[0]["failures"][0]["message"], "audio #1 dts not in [aac]");
+    assert_eq!(reports[1]["failures"][0]["rule"], "container");
+    assert_eq!(reports[1]["failures"][0]["message"], "container avi not in [mp4, mkv]");
+
+    let csv = read(&work_directory.path().join("to_transcode.csv"));
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows[0], "path,stream_index,stream_type,rule,message");
+    assert!(rows[1].ends_with("h264_dts.mkv\",1,\"audio\",audio_codec,\"audio #1 dts not in [aac]\""));
+    assert!(rows[2].ends_with("old.avi\",,,container,\"container avi not in [mp4, mkv]\""));
+}
+
+#[test]
+fn cover_art_and_attachments_follow_their_own_rules() {
+    let library = tree(&["h264_aac_poster.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+    assert_eq!(search_with_profile(&library, "[cover_art]\ncodecs = [\"png\"]\n").0, vec!["h264_aac_poster.mkv"]);
+    assert_eq!(search_with_profile(&library, "[attachment]\nallowed = false\n").0, vec!["h264_aac_poster.mkv"]);
+}
+
+#[test]
+fn applies_video_codec_constraints() {
+    let library = tree(&["h264_aac.mkv", "h264_high10.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+
+    let (recorded, csv) = search_with_profile(&library, "[video.constraints.h264]\nprofiles = [\"Main\", \"High\"]\nmax_level = \"5.1\"\nmax_bit_depth = 8\npix_fmts = [\"yuv420p\"]\n");
+    assert_eq!(recorded, vec!["h264_high10.mkv"]);
+    assert!(csv.contains("video_profile,\"video #0 h264 profile High 10 not in [Main, High]\""));
+    assert!(csv.contains("video_level,\"video #0 h264 level 5.2 above 5.1\""));
+    assert!(csv.contains("video_bit_depth,\"video #0 h264 10 bits above 8\""));
+    assert!(csv.contains("video_pixel_format,\"video #0 h264 pixel format yuv420p10le not in [yuv420p]\""));
+}
+
+#[test]
+fn applies_audio_codec_constraints() {
+    let library = tree(&["h264_aac.mkv", "h264_he_aac_71.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+
+    let (recorded, csv) = search_with_profile(&library, "[audio.constraints.aac]\nprofiles = [\"LC\"]\nmax_channels = 2\nchannel_layouts = [\"mono\", \"stereo\"]\nsample_rates = [44100, 48000]\nmin_bit_rate = 64000\nmax_bit_rate = 320000\n");
+    assert_eq!(recorded, vec!["h264_he_aac_71.mkv"]);
+    assert!(csv.contains("audio_profile,\"audio #1 aac profile HE-AAC not in [LC]\""));
+    assert!(csv.contains("audio_channels,\"audio #1 aac 8 channels above 2\""));
+    assert!(csv.contains("audio_channel_layout,\"audio #1 aac channel layout 7.1 not in [mono, stereo]\""));
+    assert!(csv.contains("audio_sample_rate,\"audio #1 aac sample rate 96000 not in [44100, 48000]\""));
+    assert!(csv.contains("audio_bit_rate,\"audio #1 aac bit rate 512000 above 320000\""));
+}
+
+#[test]
+fn matches_constrained_codecs_whatever_their_case() {
+    let library = tree(&["h264_aac.mkv", "h264_high10.mkv", "h264_he_aac_71.mkv"]);
+    let (recorded, _) = search_with_profile(&library, "[video.constraints.H264]\nmax_bit_depth = 8\n\n[audio.constraints.AAC]\nmax_channels = 2\n");
+    assert_eq!(recorded, vec!["h264_he_aac_71.mkv", "h264_high10.mkv"]);
+}
+
+#[test]
+fn matches_subtitle_languages_whatever_their_form() {
+    let library = tree(&["h264_fr_subtitles.mkv", "h264_spa_subtitle.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["h264_spa_subtitle.mkv"]);
+
+    let (recorded, csv) = search_with_profile(&library, "[subtitle]\nlanguages = [\"Spanish\", \"en\"]\n");
+    assert_eq!(recorded, vec!["h264_fr_subtitles.mkv"]);
+    assert!(csv.contains("subtitle_language,\"subtitle #2 language fr-FR not in [Spanish, en]\""));
+    assert!(csv.contains("subtitle_language,\"subtitle #3 language French not in [Spanish, en]\""));
+    assert!(!csv.contains("subtitle #4"));
+}
+
+#[test]
+fn requires_audio_in_preferred_languages() {
+    let library = tree(&["h264_aac.mkv", "h264_rus.mkv", "h264_rus_eng.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+    assert_eq!(search_with_profile(&library, "[audio]\nlanguages = [\"fra\", \"eng\"]\n").0, vec!["h264_rus.mkv"]);
+
+    let (recorded, csv) = search_with_profile(&library, "[audio]\nlanguages = [\"fra\", \"eng\"]\nrequire_default_language = true\n");
+    assert_eq!(recorded, vec!["h264_rus.mkv", "h264_rus_eng.mkv"]);
+    assert!(csv.contains(",,,audio_language,\"no audio in [fra, eng], audio languages [rus]\""));
+    assert!(csv.contains(",1,\"audio\",default_audio_language,\"audio #1 default, language ru not in [fra, eng]\""));
+}
+
+#[test]
+fn applies_subtitle_kind_rules() {
+    let library = tree(&["h264_aac.mkv", "h264_pgs_subtitle.mkv"]);
+    let (recorded, csv) = search_report(&library, &[]);
+    assert_eq!(recorded, vec!["h264_pgs_subtitle.mkv"]);
+    assert!(csv.contains("subtitle_kind,\"subtitle #2 hdmv_pgs_subtitle bitmap subtitles not allowed\""));
+    assert_eq!(search_with_profile(&library, "[subtitle]\nallow_text = false\nallow_bitmap = true\n").0, vec!["h264_aac.mkv"]);
+}
+
+#[test]
+fn applies_hdr_rules() {
+    let library = tree(&["hevc_aac.mkv", "hevc_hdr10.mkv", "hevc_hlg.mkv", "hevc_dv5.mkv", "hevc_dv8.mkv"]);
+    assert!(search_with_profile(&library, "[video]\ncodecs = [\"hevc\"]\n").0.is_empty());
+
+    let (recorded, csv) = search_with_profile(&library, "[video]\ncodecs = [\"hevc\"]\nallow_hdr10 = false\nallow_hlg = false\n");
+    assert_eq!(recorded, vec!["hevc_dv8.mkv", "hevc_hdr10.mkv", "hevc_hlg.mkv"]);
+    assert!(csv.contains(",0,\"video\",video_hdr10,\"video #0 hevc HDR10 (smpte2084 transfer) not supported\""));
+    assert!(csv.contains(",0,\"video\",video_hlg,\"video #0 hevc HLG (arib-std-b67 transfer) not supported\""));
+
+    let (recorded, csv) = search_with_profile(&library, "[video]\ncodecs = [\"hevc\"]\ndenied_dolby_vision_profiles = [5]\n");
+    assert_eq!(recorded, vec!["hevc_dv5.mkv"]);
+    assert!(csv.contains(",0,\"video\",video_dolby_vision,\"video #0 hevc Dolby Vision profile 5 not supported\""));
+
+    assert_eq!(search_with_profile(&library, "[video]\ncodecs = [\"hevc\"]\nallow_dolby_vision = false\n").0, vec!["hevc_dv5.mkv", "hevc_dv8.mkv"]);
+}
+
+#[test]
+fn records_interlaced_and_variable_frame_rate_medias() {
+    let library = tree(&["h264_aac.mkv", "dvd_interlaced.mkv", "phone_vfr.mkv", "rounded_cfr.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+    let (recorded, csv) = search_with_profile(&library, "[video]\nallow_interlaced = false\nallow_variable_frame_rate = false\n");
+    assert_eq!(recorded, vec!["dvd_interlaced.mkv", "phone_vfr.mkv"]);
+    assert!(csv.contains(",0,\"video\",video_interlaced,\"video #0 h264 interlaced (tt field order), deinterlace required\""));
+    assert!(csv.contains(",0,\"video\",video_variable_frame_rate,\"video #0 h264 variable frame rate (average 29.863 fps, base 30.000 fps), constant frame rate required\""));
+
+    assert_eq!(search_with_profile(&library, "[video]\nallow_variable_frame_rate = false\n").0, vec!["phone_vfr.mkv"]);
+    assert_eq!(search_with_profile(&library, "[video]\nallow_interlaced = false\n").0, vec!["dvd_interlaced.mkv"]);
+}
+
+#[test]
+fn records_medias_above_resolution_and_frame_rate_ceilings() {
+    let library = tree(&["h264_aac.mkv", "h264_2160p60.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+
+    let (recorded, csv) = search_with_profile(&library, "[video]\nmax_width = 1920\nmax_height = 1080\nmax_frame_rate = 30\n");
+    assert_eq!(recorded, vec!["h264_2160p60.mkv"]);
+    assert!(csv.contains(",0,\"video\",video_downscale,\"video #0 h264 3840x2160 above 1920x1080, downscale required\""));
+    assert!(csv.contains(",0,\"video\",video_downscale,\"video #0 h264 59.940 fps above 30 fps, downscale required\""));
+
+    assert!(search_with_profile(&library, "[video]\nmax_frame_rate = 59.94\n").0.is_empty());
+    assert_eq!(search(&library, &["--target", "chromecast"]), vec!["h264_2160p60.mkv"]);
+}
+
+#[test]
+fn ranks_wastefully_large_medias_by_estimated_savings() {
+    let library = tree(&["h264_aac.mkv", "large_encode.mkv", "remux.mkv"]);
+    let work_directory = TempDir::new().unwrap();
+    let output_file = work_directory.path().join("to_shrink.txt");
+    let output = run_search(&library, &output_file, &["--mode", "space-saving"], &fixtures_directory());
+    assert!(output.status.success(), "search failed : {}", stderr_of(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("2 wastefully large medias, about 30.4 GB could be saved"));
+    let ranked: Vec<String> = read(&output_file).lines().map(|line| line.rsplit('/').next().unwrap().to_string()).collect();
+    assert_eq!(ranked, vec!["remux.mkv", "large_encode.mkv"]);
+    let jsonl = read(&work_directory.path().join("to_shrink.jsonl"));
+    assert!(jsonl.contains("\"reasons\":[\"bits_per_pixel\"],\"size\":30000000000,\"video_bit_rate\":40000000,"));
+    assert!(jsonl.contains("\"target_bit_rate\":4971668,\"estimated_size\":6355875900,\"estimated_savings\":23644124100}"));
+    let csv = read(&work_directory.path().join("to_shrink.csv"));
+    assert!(csv.contains("large_encode.mkv\",\"bits_per_pixel\",10211400000,15000000,0.302,4971668,3442275900,6769124100"));
+
+    let profile = work_directory.path().join("profile.toml");
+    write_file(&profile, "[space_saving]\nmax_bits_per_pixel = 1.0\nmax_bit_rate = 20000000\ntarget_bit_rate = 8000000\n");
+    let profile_location = profile.to_string_lossy().to_string();
+    let output = run_search(&library, &output_file, &["--mode", "space-saving", "--profile", &profile_location], &fixtures_directory());
+    assert!(output.status.success(), "search failed : {}", stderr_of(&output));
+    assert_eq!(relative_lines(&output_file, &library), vec!["remux.mkv"]);
+    assert!(read(&work_directory.path().join("to_shrink.csv")).contains("remux.mkv\",\"bit_rate\",30000000000,40000000,0.805,8000000,8400000000,21600000000"));
+}
+
+#[test]
+fn checks_container_read_by_ffprobe() {
+    let library = tree(&["quicktime.mp4", "h264_aac.mp4", "h264_aac.mkv"]);
+    assert_eq!(search(&library, &[]), vec!["quicktime.mp4"]);
+
+    let (recorded, csv) = search_with_profile(&library, "[container]\nformats = []\ndenied_formats = [\"mkv\"]\n");
+    assert_eq!(recorded, vec!["h264_aac.mkv"]);
+    assert!(csv.contains("container,\"container mkv denied\""));
+}
+
+fn atom(kind: &[u8; 4], payload_size: usize) -> Vec<u8> {
+    let mut atom = ((payload_size + 8) as u32).to_be_bytes().to_vec();
+    atom.extend_from_slice(kind);
+    atom.extend(std::iter::repeat_n(0u8, payload_size));
+    return atom;
+}
+
+#[test]
+fn records_mp4_without_faststart() {
+    let library = tree(&[]);
+    let media = library.path().join("streamed.mp4");
+    std::fs::write(&media, [atom(b"ftyp", 16), atom(b"mdat", 64), atom(b"moov", 32)].concat()).unwrap();
+    assert!(search(&library, &[]).is_empty());
+    let (recorded, csv) = search_with_profile(&library, "[container]\nrequire_faststart = true\n");
+    assert_eq!(recorded, vec!["streamed.mp4"]);
+    assert!(csv.contains("faststart,\"needs faststart remux, moov atom after mdat\""));
+
+    std::fs::write(&media, [atom(b"ftyp", 16), atom(b"moov", 32), atom(b"mdat", 64)].concat()).unwrap();
+    assert!(search_with_profile(&library, "[container]\nrequire_faststart = true\n").0.is_empty());
+}
+
+#[test]
+fn reads_mp4_with_corrupt_atom_sizes() {
+    let library = tree(&[]);
+    let mut free_atom = 1u32.to_be_bytes().to_vec();
+    free_atom.extend_from_slice(b"free");
+    free_atom.extend_from_slice(&u64::MAX.to_be_bytes());
+    std::fs::write(library.path().join("streamed.mp4"), [atom(b"ftyp", 16), free_atom, atom(b"mdat", 48)].concat()).unwrap();
+    assert!(search_with_profile(&library, "[container]\nrequire_faststart = true\n").0.is_empty());
+}
+
+#[test]
+fn detects_medias_whatever_their_extension_case() {
+    let library = tree(&["HEVC_UPPER.MKV", "episode.m2ts", "h264_aac.mp4"]);
+    assert_eq!(search(&library, &[]), vec!["HEVC_UPPER.MKV", "episode.m2ts"]);
+    assert_eq!(search(&library, &["--extensions", "mkv,mp4"]), vec!["HEVC_UPPER.MKV"]);
+}
+
+#[test]
+fn sniffs_medias_without_media_extension() {
+    let library = tree(&["notes.txt"]);
+    std::fs::write(library.path().join("no_extension"), [0x1A, 0x45, 0xDF, 0xA3, 0x01, 0x00]).unwrap();
+    assert!(search(&library, &[]).is_empty());
+    assert_eq!(search(&library, &["--sniff"]), vec!["no_extension"]);
+}
+
+#[test]
+fn reports_extension_not_matching_container() {
+    let library = tree(&["mp4_named.mkv", "h264_aac.mkv"]);
+    assert!(search(&library, &[]).is_empty());
+    let (recorded, csv) = search_with_profile(&library, "[container]\nrequire_matching_extension = true\n");
+    assert_eq!(recorded, vec!["mp4_named.mkv"]);
+    assert!(csv.contains("extension_mismatch,\"extension mkv but container is mp4\""));
+}
+
+#[cfg(unix)]
+#[test]
+fn writes_paths_that_are_not_utf8_losslessly() {
+    use std::os::unix::ffi::OsStrExt;
+    let library = tree(&[]);
+    let media_name = common::latin1_name("mkv");
+    write_file(&library.path().join(&media_name), "hevc");
+    let fixtures = TempDir::new().unwrap();
+    let mut fixture_name = media_name.clone();
+    fixture_name.push(".json");
+    // Like ffprobe, the fixture copies the raw Latin-1 bytes of the name into its filename and a tag.
+    std::fs::copy(fixtures_directory().join("latin1.mkv.json"), fixtures.path().join(fixture_name)).unwrap();
+    let output_directory = TempDir::new().unwrap();
+    let output_file = output_directory.path().join("to_transcode.txt");
+
+    let escaped = run_search(&library, &output_file, &[], fixtures.path());
+    assert!(escaped.status.success(), "search failed : {}", stderr_of(&escaped));
+    assert_eq!(relative_lines(&output_file, &library), vec!["caf\\xe9.mkv"]);
+    assert!(read(&output_directory.path().join("to_transcode.jsonl")).contains("caf\\\\xe9.mkv"));
+
+    let nul = run_search(&library, &output_file, &["--list-format", "nul"], fixtures.path());
+    assert!(nul.status.success(), "search failed : {}", stderr_of(&nul));
+    let mut expected = library.path().join(&media_name).as_os_str().as_bytes().to_vec();
+    expected.push(0);
+    assert_eq!(std::fs::read(&output_file).unwrap(), expected);
+}
@@ -0,0 +1,69 @@
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{ladon_with_fixtures, fixtures_directory, stderr_of, tree};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn stats(library: &TempDir, extra_args: &[&str]) -> String {
+    return stats_with_fixtures(library, extra_args, &fixtures_directory());
+}
+
+fn stats_with_fixtures(library: &TempDir, extra_args: &[&str], fixtures: &Path) -> String {
+    let library_location = library.path().to_string_lossy().to_string();
+    let mut args = vec!["stats", &library_location];
+    args.extend_from_slice(extra_args);
+    if !extra_args.contains(&"--cache") {
+        args.push("--no-cache");
+    }
+    let output = ladon_with_fixtures(&args, fixtures);
+    assert!(output.status.success(), "stats failed : {}", stderr_of(&output));
+    return String::from_utf8_lossy(&output.stdout).to_string();
+}
+
+/// Histogram line of a category, without its padding.
+fn category_line(output: &str, title: &str, category: &str) -> String {
+    let histogram = match output.split(&format!("\n{title}\n")).nth(1) {
+        Some(histogram) => histogram.split("\n\n").next().unwrap_or_default(),
+        None => panic!("No {title} histogram in {output}"),
+    };
+    return match histogram.lines().find(|line| line.trim_start().starts_with(&format!("{category} "))) {
+        Some(line) => line.split_whitespace().collect::<Vec<&str>>().join(" "),
+        None => panic!("No {category} category in {histogram}"),
+    };
+}
+
+#[test]
+fn prints_library_histograms() {
+    let library = tree(&["h264_aac.mkv", "h264_aac.mp4", "hevc_hdr10.mkv", "hevc_dv8.mkv", "h264_fr_subtitles.mkv", "old/old.avi", "notes.txt"]);
+    let output = stats(&library, &["--jobs", "2"]);
+    assert!(output.contains("6 medias, 15.7 GB"));
+    assert_eq!(category_line(&output, "video codecs", "h264"), "h264 3 50.0% 9.0 GB ###############");
+    assert_eq!(category_line(&output, "audio codecs", "mp3"), "mp3 1 16.7% 0.7 GB #####");
+    assert_eq!(category_line(&output, "containers", "mkv"), "mkv 4 66.7% 12.0 GB ####################");
+    assert_eq!(category_line(&output, "resolutions", "2160p"), "2160p 2 33.3% 6.0 GB ##########");
+    assert_eq!(category_line(&output, "subtitle languages", "fra"), "fra 2 33.3% 6.0 GB ##########");
+    assert_eq!(category_line(&output, "dynamic range", "HDR10"), "HDR10 1 16.7% 3.0 GB #####");
+    assert_eq!(category_line(&output, "dynamic range", "Dolby Vision profile 8"), "Dolby Vision profile 8 1 16.7% 3.0 GB #####");
+    assert_eq!(category_line(&output, "active profile", "failing"), "failing 3 50.0% 6.7 GB ###############");
+}
+
+#[test]
+fn counts_failing_medias_against_the_selected_target() {
+    let library = tree(&["h264_aac.mkv", "h264_aac.mp4"]);
+    let output = stats(&library, &["--target", "apple-tv"]);
+    assert_eq!(category_line(&output, "active profile", "failing"), "failing 1 50.0% 3.0 GB ###############");
+}
+
+#[test]
+fn reuses_the_probe_cache() {
+    let library = tree(&["h264_aac.mkv", "hevc_aac.mkv"]);
+    let work_directory = TempDir::new().unwrap();
+    let cache = work_directory.path().join("probes.json");
+    let cache_location = cache.to_string_lossy().to_string();
+    let no_fixtures = TempDir::new().unwrap();
+    let probed = stats(&library, &["--cache", &cache_location]);
+    assert!(cache.is_file());
+    assert_eq!(stats_with_fixtures(&library, &["--cache", &cache_location], no_fixtures.path()), probed);
+}
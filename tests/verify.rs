@@ -0,0 +1,36 @@
+#![allow(clippy::needless_return)]
+
+mod common;
+
+use common::{ladon, read, relative_lines, stderr_of, tree};
+use tempfile::TempDir;
+
+fn verify(library: &TempDir, extra_args: &[&str]) -> (Vec<String>, String) {
+    let output_directory = TempDir::new().unwrap();
+    let output_file = output_directory.path().join("damaged.txt");
+    let output_file_location = output_file.to_string_lossy().to_string();
+    let library_location = library.path().to_string_lossy().to_string();
+    let mut args = vec!["verify", "-m", &library_location, "-o", &output_file_location];
+    args.extend_from_slice(extra_args);
+    let output = ladon(&args);
+    assert!(output.status.success(), "verify failed : {}", stderr_of(&output));
+    return (relative_lines(&output_file, library), read(&output_directory.path().join("damaged.jsonl")));
+}
+
+#[test]
+fn lists_unreadable_truncated_and_undecodable_medias() {
+    let library = tree(&["h264_aac.mkv", "truncated.mkv", "broken_frames.mkv", "broken.mkv"]);
+    let (damaged, report) = verify(&library, &[]);
+    assert_eq!(damaged, vec!["broken.mkv", "broken_frames.mkv", "truncated.mkv"]);
+    assert!(report.contains(r#"{"check":"unreadable","message":"unreadable media, ffprobe exited with 1"#));
+    assert!(report.contains(r#"{"check":"decode","message":"[h264 @ 0x55d1c3a8e2c0] error while decoding MB 53 20, bytestream -7"}"#));
+    assert!(report.contains(r#"{"check":"truncated","message":"size 1000 MB is 36% of the 2786 MB expected from duration and bit rates"}"#));
+}
+
+#[test]
+fn quick_verification_does_not_decode() {
+    let library = tree(&["h264_aac.mkv", "truncated.mkv", "broken_frames.mkv"]);
+    let (damaged, report) = verify(&library, &["--quick", "--jobs", "2"]);
+    assert_eq!(damaged, vec!["truncated.mkv"]);
+    assert!(!report.contains("decode"));
+}